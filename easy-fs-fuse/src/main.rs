@@ -0,0 +1,207 @@
+//! 在宿主机上制作和查看easy-fs镜像
+//!
+//! pack:    把user/src/bin中每个app编译出的ELF写入镜像的根目录
+//! ls:      列出镜像根目录中的文件
+//! cat:     把镜像中的一个文件输出到stdout
+//! extract: 把镜像中的所有文件解压到一个目录
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use easy_fs::{block_cache_sync_all, BlockDevice, EasyFileSystem, Inode, BLOCK_SZ, NAME_LENGTH_LIMIT};
+use std::fs::{read_dir, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::process;
+use std::sync::{Arc, Mutex};
+
+/// 镜像大小为16MiB
+const BLOCK_NUM: u32 = 16 * 2048;
+/// inode位图占用的块数，最多4096个文件
+const INODE_BITMAP_BLOCKS: u32 = 1;
+
+/// 以文件作为块设备
+struct BlockFile(Mutex<File>);
+
+impl BlockDevice for BlockFile {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        let mut file = self.0.lock().unwrap();
+        file.seek(SeekFrom::Start((block_id * BLOCK_SZ) as u64))
+            .expect("Error when seeking!");
+        assert_eq!(file.read(buf).unwrap(), BLOCK_SZ, "Not a complete block!");
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        let mut file = self.0.lock().unwrap();
+        file.seek(SeekFrom::Start((block_id * BLOCK_SZ) as u64))
+            .expect("Error when seeking!");
+        assert_eq!(file.write(buf).unwrap(), BLOCK_SZ, "Not a complete block!");
+    }
+}
+
+fn main() {
+    let image_arg = Arg::with_name("image").required(true).help("Filesystem image");
+    let matches = App::new("EasyFileSystem packer")
+        .subcommand(
+            SubCommand::with_name("pack")
+                .about("Pack the ELFs of all user apps into a new image")
+                .arg(
+                    Arg::with_name("source")
+                        .short("s")
+                        .long("source")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Executable source dir(with backslash)"),
+                )
+                .arg(
+                    Arg::with_name("target")
+                        .short("t")
+                        .long("target")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Executable target dir(with backslash)"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .takes_value(true)
+                        .help("Image path, defaults to <target>fs.img"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("ls")
+                .about("List files in the root directory of an image")
+                .arg(image_arg.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("cat")
+                .about("Write a file in an image to stdout")
+                .arg(image_arg.clone())
+                .arg(Arg::with_name("file").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("extract")
+                .about("Copy all files in an image to a directory")
+                .arg(image_arg)
+                .arg(Arg::with_name("dir").required(true)),
+        )
+        .get_matches();
+    let result = match matches.subcommand() {
+        ("pack", Some(args)) => easy_fs_pack(args),
+        ("ls", Some(args)) => open_image(args).map(|root| {
+            for name in root.ls() {
+                let inode = root.find(&name).unwrap();
+                if inode.is_dir() {
+                    println!("{:>10} {}/", "", name);
+                } else {
+                    println!("{:>10} {}", inode.size(), name);
+                }
+            }
+        }),
+        ("cat", Some(args)) => open_image(args).and_then(|root| {
+            let name = args.value_of("file").unwrap();
+            let inode = root
+                .find(name)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{}: no such file", name)))?;
+            io::stdout().write_all(&read_all(&inode))
+        }),
+        ("extract", Some(args)) => open_image(args).and_then(|root| easy_fs_extract(&root, args.value_of("dir").unwrap())),
+        _ => {
+            println!("{}", matches.usage());
+            process::exit(1);
+        }
+    };
+    if let Err(err) = result {
+        eprintln!("easy-fs-fuse: {}", err);
+        process::exit(1);
+    }
+}
+
+/// 打开已有的镜像，返回根目录
+fn open_image(args: &ArgMatches) -> io::Result<Arc<Inode>> {
+    let path = args.value_of("image").unwrap();
+    let file = OpenOptions::new().read(true).write(true).open(path)?;
+    let block_file: Arc<dyn BlockDevice> = Arc::new(BlockFile(Mutex::new(file)));
+    let efs = EasyFileSystem::open(block_file)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("{}: not an easy-fs image", path)))?;
+    Ok(Arc::new(EasyFileSystem::root_inode(&efs)))
+}
+
+fn read_all(inode: &Inode) -> Vec<u8> {
+    let mut buf = vec![0u8; inode.size()];
+    let len = inode.read_at(0, &mut buf);
+    buf.truncate(len);
+    buf
+}
+
+fn easy_fs_pack(args: &ArgMatches) -> io::Result<()> {
+    let src_path = args.value_of("source").unwrap();
+    let target_path = args.value_of("target").unwrap();
+    let image_path = args
+        .value_of("output")
+        .map(String::from)
+        .unwrap_or_else(|| format!("{}fs.img", target_path));
+    println!("src_path = {}\ntarget_path = {}\nimage = {}", src_path, target_path, image_path);
+    let block_file: Arc<dyn BlockDevice> = Arc::new(BlockFile(Mutex::new({
+        let f = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&image_path)?;
+        f.set_len((BLOCK_NUM as usize * BLOCK_SZ) as u64)?;
+        f
+    })));
+    let efs = EasyFileSystem::create(block_file, BLOCK_NUM, INODE_BITMAP_BLOCKS);
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    //与os/build.rs一致，app名为user/src/bin中去掉扩展名的文件名
+    let mut apps: Vec<String> = read_dir(src_path)?
+        .map(|dir_entry| app_name(&dir_entry?.file_name().to_string_lossy()))
+        .collect::<io::Result<_>>()?;
+    apps.sort();
+    for app in apps {
+        let mut host_file = File::open(format!("{}{}", target_path, app))?;
+        let mut all_data: Vec<u8> = Vec::new();
+        host_file.read_to_end(&mut all_data)?;
+        let inode = root_inode
+            .create(app.as_str())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::AlreadyExists, format!("{}: duplicated app", app)))?;
+        inode
+            .write_at(0, all_data.as_slice())
+            .ok_or_else(|| io::Error::other(format!("{}: no space left in the image", app)))?;
+        println!("{:>10} {}", all_data.len(), app);
+    }
+    //块缓存是写回的，退出前必须把脏块写入镜像
+    block_cache_sync_all();
+    Ok(())
+}
+
+/// user/src/bin中的源文件名对应的app名，app名超过目录项的长度限制时出错
+fn app_name(file_name: &str) -> io::Result<String> {
+    let app = file_name
+        .strip_suffix(".rs")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("{}: not an app source file", file_name)))?;
+    if app.len() > NAME_LENGTH_LIMIT {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{}: app name longer than {} bytes", app, NAME_LENGTH_LIMIT),
+        ));
+    }
+    Ok(String::from(app))
+}
+
+/// 把目录中的文件复制到dir中，子目录递归复制
+fn easy_fs_extract(root: &Inode, dir: &str) -> io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    for name in root.ls() {
+        let inode = root.find(&name).unwrap();
+        let path = Path::new(dir).join(&name);
+        if inode.is_dir() {
+            easy_fs_extract(&inode, path.to_str().unwrap())?;
+            continue;
+        }
+        let data = read_all(&inode);
+        File::create(&path)?.write_all(&data)?;
+        println!("{:>10} {}", data.len(), path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use super::{get_block_cache, BlockDevice, BLOCK_SZ};
+use alloc::sync::Arc;
+
+/// 一个位图块，共4096位
+type BitmapBlock = [u64; 64];
+
+const BLOCK_BITS: usize = BLOCK_SZ * 8;
+
+/// 从start_block_id开始、占blocks个块的位图，只有前size位有效
+pub struct Bitmap {
+    start_block_id: usize,
+    blocks: usize,
+    size: usize,
+}
+
+/// 把位编号分解为(块编号, 块内u64的下标, u64内的位)
+fn decomposition(mut bit: usize) -> (usize, usize, usize) {
+    let block_pos = bit / BLOCK_BITS;
+    bit %= BLOCK_BITS;
+    (block_pos, bit / 64, bit % 64)
+}
+
+impl Bitmap {
+    /// 最后一个位图块中通常有多余的位，它们没有对应的inode或数据块，size之后的位不会被分配
+    pub fn new(start_block_id: usize, blocks: usize, size: usize) -> Self {
+        assert!(size <= blocks * BLOCK_BITS, "bitmap of {} blocks cannot hold {} bits", blocks, size);
+        Self {
+            start_block_id,
+            blocks,
+            size,
+        }
+    }
+
+    /// 分配编号最小的空闲位，返回它的编号，前size位都已分配时返回None
+    pub fn alloc(&self, block_device: &Arc<dyn BlockDevice>) -> Option<usize> {
+        for block_id in 0..self.blocks {
+            let pos = get_block_cache(block_id + self.start_block_id, Arc::clone(block_device))
+                .lock()
+                .modify(0, |bitmap_block: &mut BitmapBlock| {
+                    if let Some((bits64_pos, inner_pos)) = bitmap_block
+                        .iter()
+                        .enumerate()
+                        .find(|(_, bits64)| **bits64 != u64::MAX)
+                        .map(|(bits64_pos, bits64)| (bits64_pos, bits64.trailing_ones() as usize))
+                    {
+                        // 前面的位都已分配，第一个空闲位超出size说明位图已满
+                        let pos = block_id * BLOCK_BITS + bits64_pos * 64 + inner_pos;
+                        if pos >= self.size {
+                            return Err(());
+                        }
+                        bitmap_block[bits64_pos] |= 1u64 << inner_pos;
+                        Ok(Some(pos))
+                    } else {
+                        Ok(None)
+                    }
+                });
+            match pos {
+                Ok(Some(pos)) => return Some(pos),
+                Ok(None) => {}
+                Err(()) => return None,
+            }
+        }
+        None
+    }
+
+    /// 释放一个已经分配的位
+    pub fn dealloc(&self, block_device: &Arc<dyn BlockDevice>, bit: usize) {
+        let (block_pos, bits64_pos, inner_pos) = decomposition(bit);
+        get_block_cache(block_pos + self.start_block_id, Arc::clone(block_device))
+            .lock()
+            .modify(0, |bitmap_block: &mut BitmapBlock| {
+                assert!(bitmap_block[bits64_pos] & (1u64 << inner_pos) > 0);
+                bitmap_block[bits64_pos] -= 1u64 << inner_pos;
+            });
+    }
+
+    /// 可以分配的位数
+    pub fn maximum(&self) -> usize {
+        self.size
+    }
+}
@@ -0,0 +1,175 @@
+use super::{BlockDevice, BLOCK_SZ};
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use lazy_static::*;
+use spin::Mutex;
+
+/// 块缓冲区，按8字节对齐，才能在上面直接放置超级块、inode和位图等结构
+#[repr(C, align(8))]
+struct BlockData([u8; BLOCK_SZ]);
+
+/// 内存中的一个块，修改过的块在sync或者被替换(drop)时写回
+pub struct BlockCache {
+    cache: BlockData,
+    block_id: usize,
+    block_device: Arc<dyn BlockDevice>,
+    modified: bool,
+}
+
+impl BlockCache {
+    /// 从设备读入一个块
+    pub fn new(block_id: usize, block_device: Arc<dyn BlockDevice>) -> Self {
+        let mut cache = BlockData([0u8; BLOCK_SZ]);
+        block_device.read_block(block_id, &mut cache.0);
+        Self {
+            cache,
+            block_id,
+            block_device,
+            modified: false,
+        }
+    }
+
+    fn addr_of_offset(&self, offset: usize) -> usize {
+        &self.cache.0[offset] as *const _ as usize
+    }
+
+    pub fn get_ref<T>(&self, offset: usize) -> &T
+    where
+        T: Sized,
+    {
+        let type_size = core::mem::size_of::<T>();
+        assert!(offset + type_size <= BLOCK_SZ);
+        let addr = self.addr_of_offset(offset);
+        unsafe { &*(addr as *const T) }
+    }
+
+    pub fn get_mut<T>(&mut self, offset: usize) -> &mut T
+    where
+        T: Sized,
+    {
+        let type_size = core::mem::size_of::<T>();
+        assert!(offset + type_size <= BLOCK_SZ);
+        self.modified = true;
+        let addr = self.addr_of_offset(offset);
+        unsafe { &mut *(addr as *mut T) }
+    }
+
+    /// 把块中offset处的内容视为T读取
+    pub fn read<T, V>(&self, offset: usize, f: impl FnOnce(&T) -> V) -> V {
+        f(self.get_ref(offset))
+    }
+
+    /// 把块中offset处的内容视为T修改
+    pub fn modify<T, V>(&mut self, offset: usize, f: impl FnOnce(&mut T) -> V) -> V {
+        f(self.get_mut(offset))
+    }
+
+    /// 修改过的块写回设备
+    pub fn sync(&mut self) {
+        if self.modified {
+            self.modified = false;
+            self.block_device.write_block(self.block_id, &self.cache.0);
+            WRITE_BACKS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for BlockCache {
+    fn drop(&mut self) {
+        self.sync()
+    }
+}
+
+/// 缓存的块数
+const BLOCK_CACHE_SIZE: usize = 16;
+
+static HITS: AtomicUsize = AtomicUsize::new(0);
+static MISSES: AtomicUsize = AtomicUsize::new(0);
+static EVICTIONS: AtomicUsize = AtomicUsize::new(0);
+static WRITE_BACKS: AtomicUsize = AtomicUsize::new(0);
+
+/// 块缓存的统计
+#[derive(Debug, Clone, Copy)]
+pub struct BlockCacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub evictions: usize,
+    /// 写回设备的次数，包括替换、sync和释放时的写回
+    pub write_backs: usize,
+}
+
+/// 按最近使用的顺序排列的块缓存，队头最久未使用
+pub struct BlockCacheManager {
+    queue: VecDeque<(usize, Arc<Mutex<BlockCache>>)>,
+}
+
+impl BlockCacheManager {
+    pub fn new() -> Self {
+        Self {
+            queue: VecDeque::new(),
+        }
+    }
+
+    pub fn get_block_cache(&mut self, block_id: usize, block_device: Arc<dyn BlockDevice>) -> Arc<Mutex<BlockCache>> {
+        if let Some(pos) = self.queue.iter().position(|(id, _)| *id == block_id) {
+            HITS.fetch_add(1, Ordering::Relaxed);
+            // 移到队尾，成为最近使用的块
+            let pair = self.queue.remove(pos).unwrap();
+            let cache = Arc::clone(&pair.1);
+            self.queue.push_back(pair);
+            return cache;
+        }
+        MISSES.fetch_add(1, Ordering::Relaxed);
+        // 替换最久未使用、且没有被其他地方持有的块，脏块在drop时写回。
+        // 多个hart同时访问文件系统时所有块都可能正在被使用，此时缓存暂时超过BLOCK_CACHE_SIZE，
+        // 之后再有块不被使用时逐渐替换回来
+        while self.queue.len() >= BLOCK_CACHE_SIZE {
+            match self.queue.iter().position(|(_, cache)| Arc::strong_count(cache) == 1) {
+                Some(idx) => {
+                    self.queue.remove(idx);
+                    EVICTIONS.fetch_add(1, Ordering::Relaxed);
+                }
+                None => break,
+            }
+        }
+        let block_cache = Arc::new(Mutex::new(BlockCache::new(block_id, Arc::clone(&block_device))));
+        self.queue.push_back((block_id, Arc::clone(&block_cache)));
+        block_cache
+    }
+}
+
+lazy_static! {
+    pub static ref BLOCK_CACHE_MANAGER: Mutex<BlockCacheManager> = Mutex::new(BlockCacheManager::new());
+}
+
+/// 获取一个块的缓存
+pub fn get_block_cache(block_id: usize, block_device: Arc<dyn BlockDevice>) -> Arc<Mutex<BlockCache>> {
+    BLOCK_CACHE_MANAGER.lock().get_block_cache(block_id, block_device)
+}
+
+/// 把所有脏块写回设备
+///
+/// 持有块的锁时会调用get_block_cache，所以不能在持有管理器的锁时再获取块的锁，
+/// 先复制出所有块再逐个写回
+pub fn block_cache_sync_all() {
+    let caches: Vec<Arc<Mutex<BlockCache>>> = BLOCK_CACHE_MANAGER
+        .lock()
+        .queue
+        .iter()
+        .map(|(_, cache)| Arc::clone(cache))
+        .collect();
+    for cache in caches {
+        cache.lock().sync();
+    }
+}
+
+pub fn block_cache_stats() -> BlockCacheStats {
+    BlockCacheStats {
+        hits: HITS.load(Ordering::Relaxed),
+        misses: MISSES.load(Ordering::Relaxed),
+        evictions: EVICTIONS.load(Ordering::Relaxed),
+        write_backs: WRITE_BACKS.load(Ordering::Relaxed),
+    }
+}
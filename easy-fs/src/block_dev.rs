@@ -0,0 +1,7 @@
+use core::any::Any;
+
+/// 以块为单位读写的设备，buf的长度为BLOCK_SZ
+pub trait BlockDevice: Send + Sync + Any {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]);
+    fn write_block(&self, block_id: usize, buf: &[u8]);
+}
@@ -0,0 +1,165 @@
+use super::{block_cache_sync_all, get_block_cache, Bitmap, BlockDevice, DiskInode, DiskInodeType, Inode, SuperBlock, BLOCK_SZ};
+use alloc::sync::Arc;
+use spin::Mutex;
+
+/// 数据块
+type DataBlock = [u8; BLOCK_SZ];
+
+/// 一个位图块可以管理的块数
+const BLOCK_BITS: u32 = BLOCK_SZ as u32 * 8;
+
+pub struct EasyFileSystem {
+    pub block_device: Arc<dyn BlockDevice>,
+    pub inode_bitmap: Bitmap,
+    pub data_bitmap: Bitmap,
+    inode_area_start_block: u32,
+    data_area_start_block: u32,
+}
+
+impl EasyFileSystem {
+    /// 在设备上创建一个新的文件系统，只有根目录
+    pub fn create(block_device: Arc<dyn BlockDevice>, total_blocks: u32, inode_bitmap_blocks: u32) -> Arc<Mutex<Self>> {
+        // 计算各个区域的大小
+        let inode_num = inode_bitmap_blocks as usize * BLOCK_BITS as usize;
+        let inode_bitmap = Bitmap::new(1, inode_bitmap_blocks as usize, inode_num);
+        let inode_area_blocks = (inode_num * core::mem::size_of::<DiskInode>()).div_ceil(BLOCK_SZ) as u32;
+        let inode_total_blocks = inode_bitmap_blocks + inode_area_blocks;
+        // 至少要有一个数据位图块和一个存放根目录的数据块
+        assert!(
+            total_blocks >= 1 + inode_total_blocks + 2,
+            "{} blocks are too few for an easy-fs with {} inode bitmap blocks, at least {} are needed",
+            total_blocks,
+            inode_bitmap_blocks,
+            1 + inode_total_blocks + 2
+        );
+        let data_total_blocks = total_blocks - 1 - inode_total_blocks;
+        // 每个数据位图块管理BLOCK_BITS个数据块，向上取整
+        let data_bitmap_blocks = (data_total_blocks + BLOCK_BITS) / (BLOCK_BITS + 1);
+        let data_area_blocks = data_total_blocks - data_bitmap_blocks;
+        let data_bitmap = Bitmap::new(
+            (1 + inode_total_blocks) as usize,
+            data_bitmap_blocks as usize,
+            data_area_blocks as usize,
+        );
+        let mut efs = Self {
+            block_device: Arc::clone(&block_device),
+            inode_bitmap,
+            data_bitmap,
+            inode_area_start_block: 1 + inode_bitmap_blocks,
+            data_area_start_block: 1 + inode_total_blocks + data_bitmap_blocks,
+        };
+        // 清空所有块
+        for i in 0..total_blocks {
+            get_block_cache(i as usize, Arc::clone(&block_device))
+                .lock()
+                .modify(0, |data_block: &mut DataBlock| {
+                    data_block.iter_mut().for_each(|byte| *byte = 0);
+                });
+        }
+        // 初始化超级块
+        get_block_cache(0, Arc::clone(&block_device))
+            .lock()
+            .modify(0, |super_block: &mut SuperBlock| {
+                super_block.initialize(
+                    total_blocks,
+                    inode_bitmap_blocks,
+                    inode_area_blocks,
+                    data_bitmap_blocks,
+                    data_area_blocks,
+                );
+            });
+        // 0号inode为根目录
+        assert_eq!(efs.alloc_inode(), Some(0));
+        let (root_inode_block_id, root_inode_offset) = efs.get_disk_inode_pos(0);
+        get_block_cache(root_inode_block_id as usize, Arc::clone(&block_device))
+            .lock()
+            .modify(root_inode_offset, |disk_inode: &mut DiskInode| {
+                disk_inode.initialize(DiskInodeType::Directory);
+            });
+        let efs = Arc::new(Mutex::new(efs));
+        // 根目录的"."和".."都指向自己
+        Self::root_inode(&efs).init_root_dirents();
+        block_cache_sync_all();
+        efs
+    }
+
+    /// 打开设备上已有的文件系统，超级块无效时返回None
+    pub fn open(block_device: Arc<dyn BlockDevice>) -> Option<Arc<Mutex<Self>>> {
+        get_block_cache(0, Arc::clone(&block_device))
+            .lock()
+            .read(0, |super_block: &SuperBlock| {
+                if !super_block.is_valid() {
+                    return None;
+                }
+                let inode_total_blocks = super_block.inode_bitmap_blocks + super_block.inode_area_blocks;
+                let inode_num = (super_block.inode_bitmap_blocks * BLOCK_BITS)
+                    .min(super_block.inode_area_blocks * (BLOCK_SZ / core::mem::size_of::<DiskInode>()) as u32);
+                let efs = Self {
+                    block_device,
+                    inode_bitmap: Bitmap::new(1, super_block.inode_bitmap_blocks as usize, inode_num as usize),
+                    data_bitmap: Bitmap::new(
+                        (1 + inode_total_blocks) as usize,
+                        super_block.data_bitmap_blocks as usize,
+                        super_block.data_area_blocks as usize,
+                    ),
+                    inode_area_start_block: 1 + super_block.inode_bitmap_blocks,
+                    data_area_start_block: 1 + inode_total_blocks + super_block.data_bitmap_blocks,
+                };
+                Some(Arc::new(Mutex::new(efs)))
+            })
+    }
+
+    /// 根目录的inode
+    pub fn root_inode(efs: &Arc<Mutex<Self>>) -> Inode {
+        let block_device = Arc::clone(&efs.lock().block_device);
+        let (block_id, block_offset) = efs.lock().get_disk_inode_pos(0);
+        Inode::new(block_id, block_offset, Arc::clone(efs), block_device)
+    }
+
+    /// inode_id号inode所在的块编号和块内偏移
+    pub fn get_disk_inode_pos(&self, inode_id: u32) -> (u32, usize) {
+        let inode_size = core::mem::size_of::<DiskInode>();
+        let inodes_per_block = (BLOCK_SZ / inode_size) as u32;
+        let block_id = self.inode_area_start_block + inode_id / inodes_per_block;
+        (block_id, (inode_id % inodes_per_block) as usize * inode_size)
+    }
+
+    /// get_disk_inode_pos的逆运算，由DiskInode的位置得到inode编号
+    pub fn get_inode_id(&self, block_id: u32, block_offset: usize) -> u32 {
+        let inode_size = core::mem::size_of::<DiskInode>();
+        let inodes_per_block = (BLOCK_SZ / inode_size) as u32;
+        (block_id - self.inode_area_start_block) * inodes_per_block + (block_offset / inode_size) as u32
+    }
+
+    /// 数据区域中第data_block_id个块在设备上的编号
+    pub fn get_data_block_id(&self, data_block_id: u32) -> u32 {
+        self.data_area_start_block + data_block_id
+    }
+
+    /// 分配一个inode，inode用完时返回None
+    pub fn alloc_inode(&mut self) -> Option<u32> {
+        self.inode_bitmap.alloc(&self.block_device).map(|inode_id| inode_id as u32)
+    }
+
+    pub fn dealloc_inode(&mut self, inode_id: u32) {
+        self.inode_bitmap.dealloc(&self.block_device, inode_id as usize)
+    }
+
+    /// 分配一个数据块，返回它在设备上的块编号，数据区域已满时返回None
+    pub fn alloc_data(&mut self) -> Option<u32> {
+        self.data_bitmap
+            .alloc(&self.block_device)
+            .map(|data_block_id| self.get_data_block_id(data_block_id as u32))
+    }
+
+    /// 清零并释放一个数据块
+    pub fn dealloc_data(&mut self, block_id: u32) {
+        get_block_cache(block_id as usize, Arc::clone(&self.block_device))
+            .lock()
+            .modify(0, |data_block: &mut DataBlock| {
+                data_block.iter_mut().for_each(|p| *p = 0);
+            });
+        self.data_bitmap
+            .dealloc(&self.block_device, (block_id - self.data_area_start_block) as usize)
+    }
+}
@@ -0,0 +1,28 @@
+//! 一个简单的块文件系统
+//!
+//! 磁盘布局依次为：超级块、inode位图、inode区域、数据块位图、数据块区域。
+//! 支持多级目录，每个目录都有"."和".."。不依赖内核的任何模块，内核和宿主机上的打包工具都可以使用。
+//! 所有块都经过块缓存访问，修改只有在缓存被替换或者调用block_cache_sync_all时才写回设备
+
+#![no_std]
+
+extern crate alloc;
+
+mod bitmap;
+mod block_cache;
+mod block_dev;
+mod efs;
+mod layout;
+mod vfs;
+
+/// 块大小
+pub const BLOCK_SZ: usize = 512;
+
+use bitmap::Bitmap;
+use block_cache::get_block_cache;
+pub use block_cache::{block_cache_stats, block_cache_sync_all, BlockCacheStats};
+pub use block_dev::BlockDevice;
+pub use efs::EasyFileSystem;
+pub use layout::NAME_LENGTH_LIMIT;
+use layout::*;
+pub use vfs::{DirEntryInfo, Inode};
@@ -0,0 +1,344 @@
+use super::{get_block_cache, BlockDevice, DirEntry, DiskInode, DiskInodeType, EasyFileSystem, DIRENT_SZ, NAME_LENGTH_LIMIT};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use spin::{Mutex, MutexGuard};
+
+/// 目录中的一项
+#[derive(Debug)]
+pub struct DirEntryInfo {
+    pub name: String,
+    pub inode_id: u32,
+    pub is_dir: bool,
+}
+
+/// 内存中的inode，指向磁盘上DiskInode的位置
+///
+/// 所有操作都先获取文件系统的锁，保证同一时刻只有一个操作在修改磁盘
+pub struct Inode {
+    block_id: usize,
+    block_offset: usize,
+    fs: Arc<Mutex<EasyFileSystem>>,
+    block_device: Arc<dyn BlockDevice>,
+}
+
+impl Inode {
+    pub fn new(block_id: u32, block_offset: usize, fs: Arc<Mutex<EasyFileSystem>>, block_device: Arc<dyn BlockDevice>) -> Self {
+        Self {
+            block_id: block_id as usize,
+            block_offset,
+            fs,
+            block_device,
+        }
+    }
+
+    fn read_disk_inode<V>(&self, f: impl FnOnce(&DiskInode) -> V) -> V {
+        get_block_cache(self.block_id, Arc::clone(&self.block_device))
+            .lock()
+            .read(self.block_offset, f)
+    }
+
+    fn modify_disk_inode<V>(&self, f: impl FnOnce(&mut DiskInode) -> V) -> V {
+        get_block_cache(self.block_id, Arc::clone(&self.block_device))
+            .lock()
+            .modify(self.block_offset, f)
+    }
+
+    /// 目录中第i个目录项
+    fn read_dirent(&self, i: usize, disk_inode: &DiskInode) -> DirEntry {
+        let mut dirent = DirEntry::empty();
+        assert_eq!(
+            disk_inode.read_at(DIRENT_SZ * i, dirent.as_bytes_mut(), &self.block_device),
+            DIRENT_SZ,
+        );
+        dirent
+    }
+
+    /// 在目录中查找name所在的目录项，返回(目录项下标, inode编号)
+    fn find_dirent(&self, name: &str, disk_inode: &DiskInode) -> Option<(usize, u32)> {
+        assert!(disk_inode.is_dir());
+        let file_count = (disk_inode.size as usize) / DIRENT_SZ;
+        (0..file_count).find_map(|i| {
+            let dirent = self.read_dirent(i, disk_inode);
+            (!dirent.is_empty() && dirent.name() == name).then(|| (i, dirent.inode_number()))
+        })
+    }
+
+    /// 在目录中查找name对应的inode编号
+    fn find_inode_id(&self, name: &str, disk_inode: &DiskInode) -> Option<u32> {
+        self.find_dirent(name, disk_inode).map(|(_, inode_id)| inode_id)
+    }
+
+    /// 在目录中加入一个目录项，优先复用被删除的目录项，没有时追加到末尾
+    ///
+    /// name太长或者目录需要扩大但没有空闲的数据块时返回false
+    fn add_dirent(&self, name: &str, inode_id: u32, disk_inode: &mut DiskInode, fs: &mut MutexGuard<EasyFileSystem>) -> bool {
+        let Some(dirent) = DirEntry::new(name, inode_id) else {
+            return false;
+        };
+        let file_count = (disk_inode.size as usize) / DIRENT_SZ;
+        let slot = (0..file_count)
+            .find(|&i| self.read_dirent(i, disk_inode).is_empty())
+            .unwrap_or(file_count);
+        if slot == file_count && !self.increase_size((file_count + 1) * DIRENT_SZ, disk_inode, fs) {
+            return false;
+        }
+        disk_inode.write_at(slot * DIRENT_SZ, dirent.as_bytes(), &self.block_device);
+        true
+    }
+
+    /// 在目录中查找name，self不是目录时返回None
+    pub fn find(&self, name: &str) -> Option<Arc<Inode>> {
+        let fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| {
+            if !disk_inode.is_dir() {
+                return None;
+            }
+            self.find_inode_id(name, disk_inode).map(|inode_id| {
+                let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+                Arc::new(Self::new(block_id, block_offset, self.fs.clone(), self.block_device.clone()))
+            })
+        })
+    }
+
+    /// 把disk_inode扩大到new_size，需要的块从fs中分配
+    ///
+    /// 超过文件大小上限或者数据块不够时返回false，此时已经分配的块被释放，文件保持不变
+    fn increase_size(&self, new_size: usize, disk_inode: &mut DiskInode, fs: &mut MutexGuard<EasyFileSystem>) -> bool {
+        if new_size > DiskInode::MAX_SIZE as usize {
+            return false;
+        }
+        let new_size = new_size as u32;
+        if new_size < disk_inode.size {
+            return true;
+        }
+        let blocks_needed = disk_inode.blocks_num_needed(new_size);
+        let mut v: Vec<u32> = Vec::new();
+        for _ in 0..blocks_needed {
+            match fs.alloc_data() {
+                Some(block_id) => v.push(block_id),
+                None => {
+                    for block_id in v {
+                        fs.dealloc_data(block_id);
+                    }
+                    return false;
+                }
+            }
+        }
+        disk_inode.increase_size(new_size, v, &self.block_device);
+        true
+    }
+
+    /// 释放inode_id号inode的所有数据块以及inode本身
+    fn free(&self, inode_id: u32, fs: &mut MutexGuard<EasyFileSystem>) {
+        self.modify_disk_inode(|disk_inode| {
+            for data_block in disk_inode.clear_size(&self.block_device) {
+                fs.dealloc_data(data_block);
+            }
+        });
+        fs.dealloc_inode(inode_id);
+    }
+
+    /// 为新创建的根目录加入"."和".."
+    pub(crate) fn init_root_dirents(&self) {
+        let mut fs = self.fs.lock();
+        self.modify_disk_inode(|disk_inode| {
+            assert!(self.add_dirent(".", 0, disk_inode, &mut fs) && self.add_dirent("..", 0, disk_inode, &mut fs));
+            disk_inode.nlink = 2;
+        });
+    }
+
+    /// 在目录中创建一个普通文件，name为空、超过NAME_LENGTH_LIMIT字节、已经存在或者inode、数据块不够时返回None
+    pub fn create(&self, name: &str) -> Option<Arc<Inode>> {
+        self.create_inode(name, DiskInodeType::File)
+    }
+
+    /// 在目录中创建一个子目录，失败的情况与create相同
+    pub fn mkdir(&self, name: &str) -> Option<Arc<Inode>> {
+        self.create_inode(name, DiskInodeType::Directory)
+    }
+
+    fn create_inode(&self, name: &str, type_: DiskInodeType) -> Option<Arc<Inode>> {
+        let mut fs = self.fs.lock();
+        if name.is_empty()
+            || name.len() > NAME_LENGTH_LIMIT
+            || self
+                .read_disk_inode(|dir| self.find_inode_id(name, dir))
+                .is_some()
+        {
+            return None;
+        }
+        let is_dir = type_ == DiskInodeType::Directory;
+        // 分配并初始化新的inode
+        let new_inode_id = fs.alloc_inode()?;
+        let (new_inode_block_id, new_inode_block_offset) = fs.get_disk_inode_pos(new_inode_id);
+        let new_inode = Arc::new(Self::new(
+            new_inode_block_id,
+            new_inode_block_offset,
+            self.fs.clone(),
+            self.block_device.clone(),
+        ));
+        new_inode.modify_disk_inode(|disk_inode| disk_inode.initialize(type_));
+        if is_dir {
+            // 子目录的链接数为2：父目录中的目录项和它自己的"."，父目录因为".."多一个链接
+            let parent_id = fs.get_inode_id(self.block_id as u32, self.block_offset);
+            let added = new_inode.modify_disk_inode(|disk_inode| {
+                disk_inode.nlink = 2;
+                new_inode.add_dirent(".", new_inode_id, disk_inode, &mut fs)
+                    && new_inode.add_dirent("..", parent_id, disk_inode, &mut fs)
+            });
+            if !added {
+                new_inode.free(new_inode_id, &mut fs);
+                return None;
+            }
+        }
+        let added = self.modify_disk_inode(|dir| {
+            let added = self.add_dirent(name, new_inode_id, dir, &mut fs);
+            if added && is_dir {
+                dir.nlink += 1;
+            }
+            added
+        });
+        if !added {
+            new_inode.free(new_inode_id, &mut fs);
+            return None;
+        }
+        Some(new_inode)
+    }
+
+    /// 在目录中创建名为name的目录项，指向target，target的链接数加一
+    ///
+    /// name为空、超过NAME_LENGTH_LIMIT字节或者已经存在、target是目录或者目录需要扩大但没有空闲的数据块时返回false
+    pub fn link(&self, name: &str, target: &Inode) -> bool {
+        let mut fs = self.fs.lock();
+        if name.is_empty()
+            || name.len() > NAME_LENGTH_LIMIT
+            || self.read_disk_inode(|dir| self.find_inode_id(name, dir)).is_some()
+            || target.read_disk_inode(|disk_inode| disk_inode.is_dir())
+        {
+            return false;
+        }
+        let target_id = fs.get_inode_id(target.block_id as u32, target.block_offset);
+        if !self.modify_disk_inode(|dir| self.add_dirent(name, target_id, dir, &mut fs)) {
+            return false;
+        }
+        target.modify_disk_inode(|disk_inode| disk_inode.nlink += 1);
+        true
+    }
+
+    /// 删除目录项name，返回它指向的inode，inode的链接数减一
+    ///
+    /// 链接数减为0时inode不会被释放，文件可能仍被打开，由调用者在合适的时候调用release。
+    /// 不能删除目录
+    pub fn unlink(&self, name: &str) -> Option<Arc<Inode>> {
+        let fs = self.fs.lock();
+        let (slot, inode_id) = self.read_disk_inode(|dir| self.find_dirent(name, dir))?;
+        let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+        let inode = Arc::new(Self::new(block_id, block_offset, self.fs.clone(), self.block_device.clone()));
+        if inode.read_disk_inode(|disk_inode| disk_inode.is_dir()) {
+            return None;
+        }
+        self.modify_disk_inode(|dir| {
+            dir.write_at(slot * DIRENT_SZ, DirEntry::empty().as_bytes(), &self.block_device);
+        });
+        inode.modify_disk_inode(|disk_inode| disk_inode.nlink -= 1);
+        Some(inode)
+    }
+
+    /// 释放inode的所有数据块以及inode本身，调用之后不能再使用这个inode
+    pub fn release(&self) {
+        let mut fs = self.fs.lock();
+        let inode_id = fs.get_inode_id(self.block_id as u32, self.block_offset);
+        assert_eq!(self.read_disk_inode(|disk_inode| disk_inode.nlink), 0);
+        self.free(inode_id, &mut fs);
+    }
+
+    /// 指向这个inode的目录项数
+    pub fn nlink(&self) -> u32 {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.nlink)
+    }
+
+    /// 列出目录中的所有目录项，包括"."和".."
+    pub fn dirents(&self) -> Vec<DirEntryInfo> {
+        let fs = self.fs.lock();
+        let entries: Vec<(String, u32)> = self.read_disk_inode(|disk_inode| {
+            let file_count = (disk_inode.size as usize) / DIRENT_SZ;
+            (0..file_count)
+                .map(|i| self.read_dirent(i, disk_inode))
+                .filter(|dirent| !dirent.is_empty())
+                .map(|dirent| (String::from(dirent.name()), dirent.inode_number()))
+                .collect()
+        });
+        entries
+            .into_iter()
+            .map(|(name, inode_id)| {
+                let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+                let is_dir = get_block_cache(block_id as usize, Arc::clone(&self.block_device))
+                    .lock()
+                    .read(block_offset, |disk_inode: &DiskInode| disk_inode.is_dir());
+                DirEntryInfo { name, inode_id, is_dir }
+            })
+            .collect()
+    }
+
+    /// 列出目录中的所有文件名，不包括"."和".."
+    pub fn ls(&self) -> Vec<String> {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| {
+            let file_count = (disk_inode.size as usize) / DIRENT_SZ;
+            (0..file_count)
+                .map(|i| self.read_dirent(i, disk_inode))
+                .filter(|dirent| !dirent.is_empty() && dirent.name() != "." && dirent.name() != "..")
+                .map(|dirent| String::from(dirent.name()))
+                .collect()
+        })
+    }
+
+    /// inode编号
+    pub fn inode_id(&self) -> u32 {
+        self.fs.lock().get_inode_id(self.block_id as u32, self.block_offset)
+    }
+
+    pub fn is_dir(&self) -> bool {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.is_dir())
+    }
+
+    /// 文件大小
+    pub fn size(&self) -> usize {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.size as usize)
+    }
+
+    pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.read_at(offset, buf, &self.block_device))
+    }
+
+    /// 从offset开始写入buf，必要时扩大文件
+    ///
+    /// 超过文件大小上限或者没有足够的数据块时什么也不写，返回None
+    pub fn write_at(&self, offset: usize, buf: &[u8]) -> Option<usize> {
+        let mut fs = self.fs.lock();
+        self.modify_disk_inode(|disk_inode| {
+            if !self.increase_size(offset + buf.len(), disk_inode, &mut fs) {
+                return None;
+            }
+            Some(disk_inode.write_at(offset, buf, &self.block_device))
+        })
+    }
+
+    /// 清空文件内容，释放所有数据块
+    pub fn clear(&self) {
+        let mut fs = self.fs.lock();
+        self.modify_disk_inode(|disk_inode| {
+            let size = disk_inode.size;
+            let data_blocks_dealloc = disk_inode.clear_size(&self.block_device);
+            assert!(data_blocks_dealloc.len() == DiskInode::total_blocks(size) as usize);
+            for data_block in data_blocks_dealloc.into_iter() {
+                fs.dealloc_data(data_block);
+            }
+        });
+    }
+}
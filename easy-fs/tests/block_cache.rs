@@ -0,0 +1,41 @@
+mod common;
+
+use common::{block_pattern, MemDevice};
+use easy_fs::{block_cache_stats, block_cache_sync_all, EasyFileSystem, BLOCK_SZ};
+use std::sync::Arc;
+
+/// 远多于缓存的16个块
+const BLOCKS: usize = 100;
+
+#[test]
+fn evicted_dirty_blocks_are_written_back() {
+    let device = Arc::new(MemDevice::new(4096));
+    let efs = EasyFileSystem::create(device.clone(), 4096, 1);
+    let file = EasyFileSystem::root_inode(&efs).create("file").unwrap();
+    let before = block_cache_stats();
+    for index in 0..BLOCKS {
+        assert_eq!(file.write_at(index * BLOCK_SZ, &block_pattern(index)), Some(BLOCK_SZ));
+    }
+    let stats = block_cache_stats();
+    assert!(stats.evictions > before.evictions);
+    assert!(stats.write_backs > before.write_backs);
+
+    let on_device = |image: &[u8], index: usize| {
+        let pattern = block_pattern(index);
+        image.chunks(BLOCK_SZ).any(|block| block == pattern.as_slice())
+    };
+    // 最早写入的块已经被替换，没有sync也已经写回设备
+    let image = device.image();
+    assert!((0..BLOCKS / 2).all(|index| on_device(&image, index)));
+    // sync之后还在缓存中的块也写回设备
+    block_cache_sync_all();
+    let image = device.image();
+    assert!((0..BLOCKS).all(|index| on_device(&image, index)));
+
+    // 被替换的块重新从设备读入
+    let mut buf = [0u8; BLOCK_SZ];
+    for index in 0..BLOCKS {
+        assert_eq!(file.read_at(index * BLOCK_SZ, &mut buf), BLOCK_SZ);
+        assert!(buf[..] == block_pattern(index)[..]);
+    }
+}
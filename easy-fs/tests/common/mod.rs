@@ -0,0 +1,51 @@
+//! 测试共用的内存块设备
+//!
+//! 块缓存是全局的，只按块编号区分块，同一个进程中只能使用一个设备，
+//! 所以每个测试文件只有一个测试函数，cargo为每个测试文件启动单独的进程
+
+#![allow(dead_code)]
+
+use easy_fs::{BlockDevice, BLOCK_SZ};
+use std::sync::Mutex;
+
+/// 放在内存中的块设备
+pub struct MemDevice(Mutex<Vec<u8>>);
+
+impl MemDevice {
+    pub fn new(blocks: usize) -> Self {
+        Self(Mutex::new(vec![0u8; blocks * BLOCK_SZ]))
+    }
+
+    /// 设备当前的全部内容，不包括块缓存中还没有写回的修改
+    pub fn image(&self) -> Vec<u8> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl BlockDevice for MemDevice {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        let data = self.0.lock().unwrap();
+        buf.copy_from_slice(&data[block_id * BLOCK_SZ..(block_id + 1) * BLOCK_SZ]);
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        let mut data = self.0.lock().unwrap();
+        data[block_id * BLOCK_SZ..(block_id + 1) * BLOCK_SZ].copy_from_slice(buf);
+    }
+}
+
+/// 镜像中的inode位图和数据块位图，位置从超级块中读出
+pub fn bitmaps(image: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let field = |index: usize| u32::from_le_bytes(image[index * 4..index * 4 + 4].try_into().unwrap()) as usize;
+    // magic total_blocks inode_bitmap_blocks inode_area_blocks data_bitmap_blocks data_area_blocks
+    let (inode_bitmap_blocks, inode_area_blocks, data_bitmap_blocks) = (field(2), field(3), field(4));
+    let inode_bitmap = BLOCK_SZ..(1 + inode_bitmap_blocks) * BLOCK_SZ;
+    let data_bitmap_start = (1 + inode_bitmap_blocks + inode_area_blocks) * BLOCK_SZ;
+    let data_bitmap = data_bitmap_start..data_bitmap_start + data_bitmap_blocks * BLOCK_SZ;
+    (image[inode_bitmap].to_vec(), image[data_bitmap].to_vec())
+}
+
+/// 第index块的测试数据，不同的块内容不同
+pub fn block_pattern(index: usize) -> Vec<u8> {
+    (0..BLOCK_SZ).map(|i| (index * 7 + i * 13 + 1) as u8).collect()
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::{bitmaps, block_pattern, MemDevice};
+use easy_fs::{block_cache_sync_all, EasyFileSystem, BLOCK_SZ, NAME_LENGTH_LIMIT};
+use std::sync::Arc;
+
+/// 超过直接索引和一级间接索引，释放时也要释放二级间接索引块
+const BLOCKS: usize = 27 + 128 + 20;
+
+#[test]
+fn unlinked_file_is_freed_on_release() {
+    let device = Arc::new(MemDevice::new(4096));
+    let efs = EasyFileSystem::create(device.clone(), 4096, 1);
+    let root = EasyFileSystem::root_inode(&efs);
+    let before = bitmaps(&device.image());
+
+    let file = root.create("a").unwrap();
+    let data: Vec<u8> = (0..BLOCKS).flat_map(block_pattern).collect();
+    assert_eq!(file.write_at(0, &data), Some(data.len()));
+    assert!(root.link("b", &file));
+    assert_eq!(file.nlink(), 2);
+    assert!(!root.link("b", &file));
+    // 名字太长时失败而不是截断
+    let long_name = "x".repeat(NAME_LENGTH_LIMIT + 1);
+    assert!(!root.link(&long_name, &file));
+    assert!(root.create(&long_name).is_none());
+    assert!(root.mkdir(&long_name).is_none());
+
+    let a = root.unlink("a").unwrap();
+    assert_eq!(a.nlink(), 1);
+    assert!(root.find("a").is_none());
+    let b = root.find("b").unwrap();
+    let mut buf = [0u8; BLOCK_SZ];
+    assert_eq!(b.read_at((BLOCKS - 1) * BLOCK_SZ, &mut buf), BLOCK_SZ);
+    assert!(buf[..] == block_pattern(BLOCKS - 1)[..]);
+
+    // 链接数减为0后文件仍然占用inode和数据块，直到release
+    let b = root.unlink("b").unwrap();
+    assert_eq!(b.nlink(), 0);
+    block_cache_sync_all();
+    assert!(bitmaps(&device.image()) != before);
+    b.release();
+    block_cache_sync_all();
+    assert!(bitmaps(&device.image()) == before);
+    assert!(root.ls().iter().all(|name| name != "a" && name != "b"));
+
+    // 不能为目录创建硬链接
+    let dir = root.mkdir("dir").unwrap();
+    assert!(!root.link("dir2", &dir));
+}
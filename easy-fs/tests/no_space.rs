@@ -0,0 +1,34 @@
+mod common;
+
+use common::{block_pattern, MemDevice};
+use easy_fs::{block_cache_sync_all, EasyFileSystem, BLOCK_SZ};
+use std::sync::Arc;
+
+/// 1个inode位图块对应1024个inode区域块，剩下的不到80块是数据区域
+const TOTAL_BLOCKS: u32 = 1100;
+
+#[test]
+fn failed_write_leaves_filesystem_unchanged() {
+    let device = Arc::new(MemDevice::new(TOTAL_BLOCKS as usize));
+    let efs = EasyFileSystem::create(device.clone(), TOTAL_BLOCKS, 1);
+    let root = EasyFileSystem::root_inode(&efs);
+    let file = root.create("file").unwrap();
+    let data: Vec<u8> = (0..40).flat_map(block_pattern).collect();
+    assert_eq!(file.write_at(0, &data), Some(data.len()));
+
+    // 数据块不够时什么也不写，已经分配的块全部归还，设备上的内容与写之前完全相同
+    block_cache_sync_all();
+    let image = device.image();
+    let more: Vec<u8> = (40..100).flat_map(block_pattern).collect();
+    assert_eq!(file.write_at(data.len(), &more), None);
+    block_cache_sync_all();
+    assert!(device.image() == image);
+    assert_eq!(file.size(), data.len());
+    let mut buf = vec![0u8; data.len()];
+    assert_eq!(file.read_at(0, &mut buf), data.len());
+    assert!(buf == data);
+
+    // 归还的块可以再次分配
+    let fits = vec![0x5au8; BLOCK_SZ * 20];
+    assert_eq!(file.write_at(data.len(), &fits), Some(fits.len()));
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use common::{bitmaps, block_pattern, MemDevice};
+use easy_fs::{block_cache_sync_all, EasyFileSystem, BLOCK_SZ};
+use std::sync::Arc;
+
+/// 直接索引27块，一级间接索引128块，其余的块使用二级间接索引
+const BLOCKS: usize = 27 + 128 + 200;
+
+#[test]
+fn write_and_read_back_through_indirect_blocks() {
+    let device = Arc::new(MemDevice::new(4096));
+    let efs = EasyFileSystem::create(device.clone(), 4096, 1);
+    let root = EasyFileSystem::root_inode(&efs);
+    let file = root.create("file").unwrap();
+    let (_, data_bitmap) = bitmaps(&device.image());
+
+    // 分多次写入，每次都不在块的边界上结束
+    let data: Vec<u8> = (0..BLOCKS).flat_map(block_pattern).collect();
+    let chunk_size = BLOCK_SZ * 3 + 100;
+    for (i, chunk) in data.chunks(chunk_size).enumerate() {
+        assert_eq!(file.write_at(i * chunk_size, chunk), Some(chunk.len()));
+    }
+    assert_eq!(file.size(), data.len());
+    let mut buf = vec![0u8; data.len()];
+    assert_eq!(file.read_at(0, &mut buf), data.len());
+    assert!(buf == data);
+    assert_eq!(file.read_at(data.len(), &mut buf), 0);
+
+    // 写回之后重新打开，内容不变
+    block_cache_sync_all();
+    let efs = EasyFileSystem::open(device.clone()).unwrap();
+    let file = EasyFileSystem::root_inode(&efs).find("file").unwrap();
+    buf.fill(0);
+    assert_eq!(file.read_at(0, &mut buf), data.len());
+    assert!(buf == data);
+
+    // 清空文件释放所有数据块和索引块
+    file.clear();
+    assert_eq!(file.size(), 0);
+    block_cache_sync_all();
+    assert!(bitmaps(&device.image()).1 == data_bitmap);
+}
@@ -1,53 +1,152 @@
-// 生成link_APP.S
-use std::fs::{File, read_dir};
-use std::io::{Result,Write};
+// 把用户程序和数据文件打包成newc格式的cpio归档，生成把它嵌入内核的link_app.S
+use std::fs::{self, read_dir, File};
+use std::io::{Result, Write};
+use std::path::Path;
 
 const USER_APP_PATH: &str = "../user/target/riscv64gc-unknown-none-elf/release/";
+/// app的源文件，每个.rs文件是一个app
+const USER_SRC_PATH: &str = "../user/src/bin";
+/// 可选的数据文件目录，其中的内容打包到归档的data目录下
+const USER_DATA_PATH: &str = "../user/data";
+/// 生成的归档，与easy-fs-fuse生成的fs.img放在一起
+const INITRAMFS_NAME: &str = "initramfs.cpio";
+
+/// 文件类型和权限，与st_mode一致
+const MODE_DIR: u32 = 0o040755;
+const MODE_EXEC: u32 = 0o100755;
+const MODE_FILE: u32 = 0o100644;
 
 fn main() {
-    create_asm().unwrap();
+    let apps = app_names();
+    println!("current apps: {:?}", apps);
+    // 归档的内容变化时重新生成，否则内核会嵌入旧的归档
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed={}", USER_SRC_PATH);
+    println!("cargo:rerun-if-changed={}", USER_DATA_PATH);
+    for app in &apps {
+        println!("cargo:rerun-if-changed={}{}", USER_APP_PATH, app);
+    }
+    let archive = create_initramfs(&apps).unwrap();
+    create_asm(&archive).unwrap();
 }
 
-fn create_asm() -> Result<()>  {
-    let mut file = File::create("src/link_app.S").expect("Error: Fail to create src/link_app.S");
-    let mut apps : Vec<String>= read_dir("../user/src/bin").unwrap()
-        .map(| entry| -> String {
-            /*
-            let mut file_name = entry.unwrap().file_name().into_string().unwrap();
-            let split:Vec<_> = file_name.split('.').collect();
-            let result = String::from(split[0]);
-            println!("split:{:?}",result);
-            result*/
-            let mut name_with_ext = entry.unwrap().file_name().into_string().unwrap();
-            //println!("{}",name_with_ext);
-            name_with_ext.drain(name_with_ext.find('.').unwrap()..name_with_ext.len());
-            name_with_ext
-        }).collect();
+/// user/src/bin中按文件名排序的app，内核按同样的顺序运行它们；不是.rs的文件被忽略
+fn app_names() -> Vec<String> {
+    let mut apps: Vec<String> = read_dir(USER_SRC_PATH)
+        .unwrap()
+        .filter_map(|entry| {
+            let name = entry.unwrap().file_name().into_string().unwrap();
+            name.strip_suffix(".rs").map(String::from)
+        })
+        .collect();
     apps.sort();
-    println!("current apps: {:?}",apps);
-    writeln!(
-        file,
-        "    .align 3
-    .section .data
-    .global _num_app
-_num_app:
-    .quad {}",
-        apps.len()
-    )?;
-    for i in 0..apps.len() {
-        writeln!(file, "    .quad app_{}_start",i)?;
+    apps
+}
+
+/// newc格式的cpio归档
+struct CpioWriter {
+    data: Vec<u8>,
+    next_ino: u32,
+}
+
+impl CpioWriter {
+    fn new() -> Self {
+        Self {
+            data: Vec::new(),
+            next_ino: 1,
+        }
     }
-    writeln!(file, "    .quad app_{}_end",apps.len() - 1)?;
 
-    for (i,app) in apps.iter().enumerate() {
-        writeln!(file,"
-    .section .data
-    .global app_{i}_start
-    .global app_{i}_end
-app_{i}_start:
-    .incbin \"{USER_APP_PATH}{app}.bin\"
-app_{i}_end:")?
+    /// newc格式中文件名和文件内容都按4字节对齐
+    fn pad(&mut self) {
+        while self.data.len() % 4 != 0 {
+            self.data.push(0);
+        }
+    }
+
+    /// 写入一项，分配新的inode编号
+    fn entry(&mut self, name: &str, mode: u32, content: &[u8]) {
+        let ino = self.next_ino;
+        self.next_ino += 1;
+        self.write(ino, name, mode, content);
+    }
+
+    /// mtime等字段都为0，同样的输入总是生成同样的归档
+    fn write(&mut self, ino: u32, name: &str, mode: u32, content: &[u8]) {
+        let nlink = if mode == MODE_DIR { 2 } else { 1 };
+        // ino mode uid gid nlink mtime filesize devmajor devminor rdevmajor rdevminor namesize check
+        let fields = [ino, mode, 0, 0, nlink, 0, content.len() as u32, 0, 0, 0, 0, name.len() as u32 + 1, 0];
+        self.data.extend_from_slice(b"070701");
+        for field in fields {
+            self.data.extend_from_slice(format!("{:08X}", field).as_bytes());
+        }
+        self.data.extend_from_slice(name.as_bytes());
+        self.data.push(0);
+        self.pad();
+        self.data.extend_from_slice(content);
+        self.pad();
     }
 
+    /// 递归加入目录dir中的内容，在归档中的路径以prefix开头
+    fn add_dir(&mut self, dir: &Path, prefix: &str) -> Result<()> {
+        self.entry(prefix, MODE_DIR, &[]);
+        let mut entries: Vec<_> = read_dir(dir)?.collect::<Result<_>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let name = format!("{}/{}", prefix, entry.file_name().into_string().unwrap());
+            if entry.file_type()?.is_dir() {
+                self.add_dir(&entry.path(), &name)?;
+            } else {
+                self.entry(&name, MODE_FILE, &fs::read(entry.path())?);
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.write(0, "TRAILER!!!", 0, &[]);
+        self.data
+    }
+}
+
+/// app放在bin目录下，user/data存在时其中的文件放在data目录下，返回生成的归档
+fn create_initramfs(apps: &[String]) -> Result<Vec<u8>> {
+    let mut cpio = CpioWriter::new();
+    cpio.entry("bin", MODE_DIR, &[]);
+    for app in apps {
+        let elf = fs::read(format!("{}{}", USER_APP_PATH, app))?;
+        cpio.entry(&format!("bin/{}", app), MODE_EXEC, &elf);
+    }
+    if Path::new(USER_DATA_PATH).is_dir() {
+        cpio.add_dir(Path::new(USER_DATA_PATH), "data")?;
+    }
+    let archive = cpio.finish();
+    fs::write(format!("{}{}", USER_APP_PATH, INITRAMFS_NAME), &archive)?;
+    Ok(archive)
+}
+
+/// FNV-1a哈希
+fn checksum(data: &[u8]) -> u64 {
+    data.iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x100_0000_01b3))
+}
+
+fn create_asm(archive: &[u8]) -> Result<()> {
+    let mut file = File::create("src/link_app.S").expect("Error: Fail to create src/link_app.S");
+    //整个归档作为一段数据嵌入内核，由内核在启动时解包。
+    //rustc不跟踪.incbin引用的文件，注释中记录归档的大小和哈希，归档变化时link_app.S随之变化，内核才会重新编译
+    writeln!(
+        file,
+        "    # {INITRAMFS_NAME}: {} bytes, fnv1a {:016x}
+    .section .data
+    .align 3
+    .global initramfs_start
+    .global initramfs_end
+initramfs_start:
+    .incbin \"{USER_APP_PATH}{INITRAMFS_NAME}\"
+initramfs_end:",
+        archive.len(),
+        checksum(archive)
+    )?;
     Ok(())
 }
@@ -1,12 +1,22 @@
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
 use core::arch::asm;
 use core::slice;
+use crate::fs::{dir_files, read_all, Inode, INITRAMFS_PATH};
 use lazy_static::*;
-use crate::sync::UPSafeCell;
+use log::{debug, error, info};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crate::smp::{hart_id, online_harts, MAX_HARTS};
 use crate::trap::TrapContext;
+use xmas_elf::program::{ProgramHeader, Type};
+use xmas_elf::ElfFile;
 
 const MAX_APP_NUM:usize = 1024;
-const APP_BASE_ADDRESS:usize = 0x80400000;
-const APP_MAX_SIZE:usize = 0x20000;
+pub const APP_BASE_ADDRESS:usize = 0x80400000;
+pub const APP_MAX_SIZE:usize = 0x20000;
 const KERNEL_STACK_SIZE:usize = 4096 *2;
 const USER_STACK_SIZE:usize = 4096 *2;
 
@@ -20,13 +30,17 @@ struct UserStack {
     data: [u8; USER_STACK_SIZE],
 }
 
-static KERNEL_STACK: KernelStack = KernelStack {
+const EMPTY_KERNEL_STACK: KernelStack = KernelStack {
     data: [0; KERNEL_STACK_SIZE],
 };
-static USER_STACK: UserStack = UserStack {
+const EMPTY_USER_STACK: UserStack = UserStack {
     data: [0; USER_STACK_SIZE],
 };
 
+//每个hart同时运行一个app，各自使用自己的内核栈和用户栈
+static KERNEL_STACK: [KernelStack; MAX_HARTS] = [EMPTY_KERNEL_STACK; MAX_HARTS];
+static USER_STACK: [UserStack; MAX_HARTS] = [EMPTY_USER_STACK; MAX_HARTS];
+
 impl KernelStack {
     fn get_sp(&self) -> usize {
         self.data.as_ptr() as usize + KERNEL_STACK_SIZE
@@ -51,108 +65,235 @@ impl UserStack {
 
 struct AppManager {
     app_num:usize,
-    current_app:usize,
-    app_start:[usize;MAX_APP_NUM + 1],
+    //多个hart并发地领取app，用原子变量分配app编号，加载app时不需要持有全局锁
+    current_app:AtomicUsize,
+    //app所在的目录
+    app_dir:String,
+    //目录中按文件名排序的app
+    apps:Vec<(String, Arc<dyn Inode>)>,
 }
 
-
+/// 目录中的ELF文件，按文件名排序
+///
+/// 测试数据放在子目录中，app运行时也可能在根目录创建文件，这些都不是app
+fn elf_files(dir: &str) -> Vec<(String, Arc<dyn Inode>)> {
+    dir_files(dir)
+        .into_iter()
+        .filter(|(_, inode)| {
+            let mut magic = [0u8; 4];
+            matches!(inode.read_at(0, &mut magic), Ok(4)) && magic == *b"\x7fELF"
+        })
+        .collect()
+}
 
 impl AppManager {
-    unsafe fn new() -> AppManager {
-        extern "C" {
-            fn _num_app();
+    fn new() -> AppManager {
+        //app的链接地址由它在user/src/bin中的排序决定，磁盘上和initramfs中的app也按文件名排序
+        //磁盘根目录中有app时优先运行它们，否则运行initramfs中的app
+        let mut app_dir = String::from("/");
+        let mut apps = elf_files(&app_dir);
+        if apps.is_empty() {
+            app_dir = format!("{}/bin", INITRAMFS_PATH);
+            apps = elf_files(&app_dir);
         }
-        //无法在编译时确定数组大小,可以使用Vec
-        //let mut app_starts:Vec<usize>;
-        let num_app_ptr = _num_app as usize as *const usize;
-        let app_num = unsafe { num_app_ptr.read_volatile() };
-        let mut app_start: [usize; MAX_APP_NUM + 1] = [0; MAX_APP_NUM + 1];
-        let slice = slice::from_raw_parts(num_app_ptr.add(1), app_num + 1);
-        app_start[..app_num + 1].copy_from_slice(slice);
+        assert!(apps.len() <= MAX_APP_NUM, "[kernel] too many apps in {}", app_dir);
         AppManager {
-            app_num,
-            current_app: 0,
-            app_start,
+            app_num: apps.len(),
+            current_app: AtomicUsize::new(0),
+            app_dir,
+            apps,
         }
     }
 
-    unsafe fn load_app(&mut self, app_id:usize) {
-        if app_id >= self.app_num {
-            //panic!("app_id exceed max limit {}, invalid app_id: {}", self.app_num - 1, app_id);
-            //这里需要让os自动退出而不是panic
-            //qemu退出的代码已经给出了
-            println!("All applications completed!");
-            use crate::board::QEMUExit;
-            crate::board::QEMU_EXIT_HANDLE.exit_success();
+    /// app的ELF文件，initramfs中的app直接使用内核镜像中的数据，其他的读入堆中
+    fn app_data(&self, app_id: usize) -> Cow<'static, [u8]> {
+        let inode = &self.apps[app_id].1;
+        match inode.image() {
+            Some(image) => Cow::Borrowed(image),
+            None => Cow::Owned(read_all(inode)),
         }
-        println!("[kernel] Loading app_{}", app_id);
-        //下面需要把程序复制到APP_BASE_ADDRESS下
-        //首先清空这个区域
-        (APP_BASE_ADDRESS .. APP_MAX_SIZE + APP_MAX_SIZE).for_each(
+    }
+
+    /// 加载app的ELF文件，返回app的初始TrapContext，文件不是有效的ELF或者段不能放进app的区域时返回None
+    unsafe fn load_app(&self, app_id:usize) -> Option<TrapContext> {
+        let name = &self.apps[app_id].0;
+        info!("Loading app_{} ({})", app_id, name);
+        //每个app链接在各自的地址APP_BASE_ADDRESS + app_id * APP_MAX_SIZE，多个hart可以同时运行不同的app
+        //首先清空这个区域，.bss不在ELF文件中，也依赖这里的清零
+        let app_base = get_base_address(app_id);
+        (app_base .. app_base + APP_MAX_SIZE).for_each(
             |byte| unsafe {(byte as *mut u8).write_volatile(0)});
-        //然后复制数据到指定位置
-        //data_dst必须是可变切片
-        let data_src = slice::from_raw_parts(
-            self.app_start[app_id] as *const u8,
-            self.app_start[app_id + 1] - self.app_start[app_id]);
-        let data_dst = slice::from_raw_parts_mut(
-            APP_BASE_ADDRESS as *mut u8,
-            data_src.len());
-        data_dst.copy_from_slice(data_src);
+        let elf_data = self.app_data(app_id);
+        let elf_data = elf_data.as_ref();
+        let elf = match ElfFile::new(elf_data) {
+            Ok(elf) => elf,
+            Err(err) => {
+                error!("app_{} ({}) is not a valid ELF: {}", app_id, name, err);
+                return None;
+            }
+        };
+        //然后把每个LOAD段复制到它的虚拟地址（没有开启分页，虚拟地址即物理地址）
+        //磁盘上的app按文件名排序决定编号，改名或者放入新的ELF后可能链接在别的app的区域，只拒绝这个app
+        let mut tls = None;
+        for ph in elf.program_iter() {
+            match ph.get_type() {
+                Ok(Type::Load) => {
+                    let start = ph.virtual_addr() as usize;
+                    let in_region = start
+                        .checked_add(ph.mem_size() as usize)
+                        .is_some_and(|end| start >= app_base && end <= app_base + APP_MAX_SIZE);
+                    if !in_region {
+                        error!(
+                            "app_{} ({}) segment at {:#x} ({:#x} bytes) is out of app region [{:#x}, {:#x})",
+                            app_id, name, start, ph.mem_size(), app_base, app_base + APP_MAX_SIZE
+                        );
+                        return None;
+                    }
+                    let Some(data_src) = segment_data(elf_data, &ph) else {
+                        error!("app_{} ({}) has a truncated or malformed segment at {:#x}", app_id, name, start);
+                        return None;
+                    };
+                    //data_dst必须是可变切片
+                    let data_dst = slice::from_raw_parts_mut(start as *mut u8, data_src.len());
+                    data_dst.copy_from_slice(data_src);
+                }
+                Ok(Type::Tls) => tls = Some(ph),
+                _ => {}
+            }
+        }
         //最后清空指令缓存
         asm!("fence.i");
+
+        let mut user_sp = USER_STACK[hart_id()].get_sp();
+        let mut tp = 0;
+        if let Some(ph) = tls {
+            //在用户栈顶为app的TLS块分配空间：先放.tdata的初始值，后面跟着清零的.tbss
+            //RISC-V的TLS采用variant I且TCB大小为0，tp直接指向TLS块的起始位置
+            //TLS块最多占用一半的用户栈
+            let align = (ph.align() as usize).max(16);
+            let size = ph.mem_size() as usize;
+            let tdata = match segment_data(elf_data, &ph) {
+                Some(tdata) if align.is_power_of_two() && size.saturating_add(align) <= USER_STACK_SIZE / 2 => tdata,
+                _ => {
+                    error!("app_{} ({}) has a malformed or oversized TLS segment", app_id, name);
+                    return None;
+                }
+            };
+            let tls_start = (user_sp - size) & !(align - 1);
+            let block = slice::from_raw_parts_mut(tls_start as *mut u8, size);
+            block[..tdata.len()].copy_from_slice(tdata);
+            block[tdata.len()..].fill(0);
+            tp = tls_start;
+            user_sp = tls_start;
+        }
+        let mut cx = TrapContext::app_init_context(
+            elf.header.pt2.entry_point() as usize,
+            user_sp
+        );
+        cx.set_tp(tp);
+        Some(cx)
     }
 
     pub fn print_app_info(&self) {
-        println!("[kernel] app nums: {}", self.app_num);
-        for i in 0..self.app_num {
-            //16进制输出
-            println!("[kernel] app_{} start: {:#x}, end: {:#x}",
-                     i, self.app_start[i],self.app_start[i+1]-1);
+        info!("app nums: {} (from {})", self.app_num, self.app_dir);
+        for (i, (name, inode)) in self.apps.iter().enumerate() {
+            debug!("app_{}: {} ({} bytes)", i, name, inode.size());
         }
     }
 
-    pub fn get_current_app(&self) -> usize {
-        self.current_app
+    ///领取下一个要运行的app，返回它的编号
+    pub fn fetch_next_app(&self) -> usize {
+        self.current_app.fetch_add(1, Ordering::Relaxed)
     }
+}
 
-    pub fn move_to_next_app(&mut self) {
-        self.current_app += 1;
+/// 段在ELF文件中的内容，file_size大于mem_size或者超出文件时返回None
+fn segment_data<'a>(elf_data: &'a [u8], ph: &ProgramHeader) -> Option<&'a [u8]> {
+    if ph.file_size() > ph.mem_size() {
+        return None;
     }
+    let start = usize::try_from(ph.offset()).ok()?;
+    let end = start.checked_add(usize::try_from(ph.file_size()).ok()?)?;
+    elf_data.get(start..end)
+}
+
+/// app_id号app的链接地址，与user/build.py一致
+fn get_base_address(app_id: usize) -> usize {
+    APP_BASE_ADDRESS + app_id * APP_MAX_SIZE
 }
 
 lazy_static! {
-    static ref APP_MANAGER: UPSafeCell<AppManager> = unsafe{
-        UPSafeCell::new(AppManager::new())
-    };
+    static ref APP_MANAGER: AppManager = AppManager::new();
+}
+
+/// 已经没有app可以运行的hart数量
+static IDLE_HARTS: AtomicUsize = AtomicUsize::new(0);
+
+/// 要运行的app数量
+pub fn app_num() -> usize {
+    APP_MANAGER.app_num
+}
+
+/// 所有hart的内核栈所在的区域[start, end)
+pub fn kernel_stack_range() -> (usize, usize) {
+    let start = KERNEL_STACK.as_ptr() as usize;
+    (start, start + core::mem::size_of_val(&KERNEL_STACK))
+}
+
+/// 所有hart的用户栈所在的区域[start, end)
+pub fn user_stack_range() -> (usize, usize) {
+    let start = USER_STACK.as_ptr() as usize;
+    (start, start + core::mem::size_of_val(&USER_STACK))
 }
 
 pub fn print_app_info() {
-    APP_MANAGER.exclusive_borrow().print_app_info();
+    APP_MANAGER.print_app_info();
 }
 
-pub fn run_next_app() -> !{
-    let mut manager = APP_MANAGER.exclusive_borrow();
-    let current_app = manager.get_current_app();
-    //加载当前的app数据
-    unsafe {
-        manager.load_app(current_app);
+/// 所有app都已被领取，当前hart不再有事可做
+///
+/// 最后一个空闲下来的hart负责关机或重启，其余hart停在wfi
+fn all_apps_completed() -> ! {
+    if IDLE_HARTS.fetch_add(1, Ordering::AcqRel) + 1 == online_harts() {
+        //这里需要让os自动退出而不是panic
+        info!("All applications completed!");
+        //批处理压力测试：编译时设置REBOOT_TIMES=n，跑完所有app后重启，共重启n次
+        let reboot_times = option_env!("REBOOT_TIMES")
+            .and_then(|times| times.parse::<usize>().ok())
+            .unwrap_or(0);
+        if crate::board::boot_count() <= reboot_times {
+            info!("Rebooting ({}/{})", crate::board::boot_count(), reboot_times);
+            crate::power::reboot(false, false);
+        }
+        crate::power::shutdown(false);
     }
+    loop {
+        unsafe { asm!("wfi") };
+        //外部中断会送到所有hart，空闲的hart也要领取并处理，否则它会一直挂起，wfi立即返回
+        crate::smp::clear_ipi();
+        crate::drivers::handle_external_interrupt();
+    }
+}
 
-    manager.move_to_next_app();
-    //需要提前drop
-    drop(manager);
+pub fn run_next_app() -> !{
+    //关闭上一个app打开的文件
+    crate::task::reset_current_task();
+    //加载当前的app数据，每个app有自己的内存区域，多个hart可以同时加载；无法加载的app被跳过
+    let app_cx = loop {
+        let current_app = APP_MANAGER.fetch_next_app();
+        if current_app >= APP_MANAGER.app_num {
+            all_apps_completed();
+        }
+        if let Some(app_cx) = unsafe { APP_MANAGER.load_app(current_app) } {
+            break app_cx;
+        }
+    };
     extern "C" {
         //传入context的地址
         fn __restore(cx_addr: usize);
     }
     unsafe {
-        __restore(KERNEL_STACK.push_context(
-            TrapContext::app_init_context(
-                APP_BASE_ADDRESS,
-                USER_STACK.get_sp()
-            )) as *const _ as usize
-        );
+        __restore(KERNEL_STACK[hart_id()].push_context(app_cx) as *const _ as usize);
     }
     panic!("Unreachable in batch::run_current_app!");
 }
@@ -0,0 +1,222 @@
+//! 从设备树中得到的机器信息
+//!
+//! 启动hart在init中解析SBI传入的DTB，之后其他模块通过`machine()`读取，
+//! 没有DTB或解析失败时保留QEMU virt的默认值
+
+use crate::fdt::{Fdt, Node};
+use crate::sync::SpinMutex;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use log::{info, warn};
+
+/// 最多记录的内存区域数量
+const MAX_MEMORY_REGIONS: usize = 4;
+/// 最多记录的virtio-mmio设备数量，QEMU virt有8个
+pub const MAX_VIRTIO_DEVICES: usize = 8;
+
+/// 一个MMIO设备
+#[derive(Debug, Clone, Copy)]
+pub struct MmioDevice {
+    pub base: usize,
+    pub size: usize,
+    /// 设备连接到PLIC的中断号，0表示没有
+    pub irq: u32,
+}
+
+impl MmioDevice {
+    const fn new(base: usize, size: usize, irq: u32) -> Self {
+        Self { base, size, irq }
+    }
+
+    fn from_node(node: &Node) -> Option<Self> {
+        let (base, size) = node.reg().next()?;
+        Some(Self::new(base, size, node.interrupt().unwrap_or(0)))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MachineInfo {
+    /// 设备树是否解析成功，为false时下面都是QEMU virt的默认值
+    pub from_dtb: bool,
+    pub model: &'static str,
+    memory: [(usize, usize); MAX_MEMORY_REGIONS],
+    memory_count: usize,
+    /// time CSR每秒增加的值
+    pub timebase_frequency: usize,
+    pub harts: usize,
+    pub uart: Option<MmioDevice>,
+    pub plic: Option<MmioDevice>,
+    virtio: [MmioDevice; MAX_VIRTIO_DEVICES],
+    virtio_count: usize,
+    /// sifive_test设备的地址，用于关机和重启
+    pub test_device: Option<usize>,
+    pub bootargs: &'static str,
+    /// /chosen中的rng-seed，由QEMU等引导程序提供的随机字节，没有时为空
+    pub rng_seed: &'static [u8],
+}
+
+impl MachineInfo {
+    /// QEMU virt (-m 128M)的默认配置
+    const fn qemu_virt() -> Self {
+        const NO_DEVICE: MmioDevice = MmioDevice::new(0, 0, 0);
+        Self {
+            from_dtb: false,
+            model: "riscv-virtio,qemu",
+            memory: [(0x8000_0000, 0x800_0000), (0, 0), (0, 0), (0, 0)],
+            memory_count: 1,
+            timebase_frequency: 10_000_000,
+            //未知时按最大值处理，启动其他hart时再通过HSM确认
+            harts: crate::smp::MAX_HARTS,
+            uart: Some(MmioDevice::new(0x1000_0000, 0x100, 10)),
+            plic: Some(MmioDevice::new(0xc00_0000, 0x60_0000, 0)),
+            virtio: [NO_DEVICE; MAX_VIRTIO_DEVICES],
+            virtio_count: 0,
+            test_device: Some(0x10_0000),
+            bootargs: "",
+            rng_seed: &[],
+        }
+    }
+
+    /// 物理内存区域(起始地址, 大小)
+    pub fn memory(&self) -> &[(usize, usize)] {
+        &self.memory[..self.memory_count]
+    }
+
+    /// 按地址排序的virtio-mmio设备
+    pub fn virtio(&self) -> &[MmioDevice] {
+        &self.virtio[..self.virtio_count]
+    }
+
+    fn parse(fdt: &Fdt<'static>) -> Result<Self, crate::fdt::FdtError> {
+        let mut info = Self {
+            from_dtb: true,
+            memory_count: 0,
+            harts: 0,
+            uart: None,
+            plic: None,
+            test_device: None,
+            ..Self::qemu_virt()
+        };
+        fdt.for_each_node(|node| {
+            if node.depth == 0 {
+                if let Some(model) = node.prop_str("model") {
+                    info.model = model;
+                }
+            } else if node.prop_str("device_type") == Some("memory") {
+                for region in node.reg() {
+                    if info.memory_count < MAX_MEMORY_REGIONS {
+                        info.memory[info.memory_count] = region;
+                        info.memory_count += 1;
+                    }
+                }
+            } else if node.prop_str("device_type") == Some("cpu") {
+                //timebase-frequency也可能写在每个cpu节点上，为0时保留默认值，日志的时间戳要除以它
+                if let Some(freq) = node.prop_usize("timebase-frequency").filter(|&freq| freq != 0) {
+                    info.timebase_frequency = freq;
+                }
+                if node.prop_str("status").map_or(true, |status| status == "okay") {
+                    info.harts += 1;
+                }
+            } else if node.depth == 1 && node.base_name() == "cpus" {
+                if let Some(freq) = node.prop_usize("timebase-frequency").filter(|&freq| freq != 0) {
+                    info.timebase_frequency = freq;
+                }
+            } else if node.depth == 1 && node.base_name() == "chosen" {
+                if let Some(bootargs) = node.prop_str("bootargs") {
+                    info.bootargs = bootargs;
+                }
+                if let Some(rng_seed) = node.prop("rng-seed") {
+                    info.rng_seed = rng_seed;
+                }
+            } else if node.is_compatible("ns16550a") {
+                info.uart = info.uart.or(MmioDevice::from_node(node));
+            } else if node.is_compatible("riscv,plic0") || node.is_compatible("sifive,plic-1.0.0") {
+                info.plic = MmioDevice::from_node(node);
+            } else if node.is_compatible("sifive,test0") || node.is_compatible("sifive,test1") {
+                info.test_device = node.reg().next().map(|(base, _)| base);
+            } else if node.is_compatible("virtio,mmio") {
+                if let Some(device) = MmioDevice::from_node(node) {
+                    if info.virtio_count < MAX_VIRTIO_DEVICES {
+                        info.virtio[info.virtio_count] = device;
+                        info.virtio_count += 1;
+                    }
+                }
+            }
+        })?;
+        //QEMU按地址从高到低生成virtio节点，排序后下标与设备地址的顺序一致
+        info.virtio[..info.virtio_count].sort_unstable_by_key(|device| device.base);
+        info.harts = info.harts.max(1);
+        Ok(info)
+    }
+}
+
+static MACHINE: SpinMutex<MachineInfo> = SpinMutex::new(MachineInfo::qemu_virt());
+
+/// 日志的时间戳每次都要用到，单独保存避免加锁
+static TIMEBASE_FREQUENCY: AtomicUsize = AtomicUsize::new(10_000_000);
+static TEST_DEVICE: AtomicUsize = AtomicUsize::new(0x10_0000);
+
+/// 解析SBI传入的DTB并打印机器信息，由启动hart在启动其他hart之前调用
+pub fn init(dtb: usize) {
+    //SBI没有传入DTB时a1为0
+    let parsed = match dtb {
+        0 => Err(crate::fdt::FdtError::BadMagic),
+        _ => unsafe { Fdt::from_addr(dtb) }.and_then(|fdt| MachineInfo::parse(&fdt)),
+    };
+    let info = match parsed {
+        Ok(info) => info,
+        Err(err) => {
+            warn!("invalid device tree at {:#x}: {:?}, assuming QEMU virt", dtb, err);
+            MachineInfo::qemu_virt()
+        }
+    };
+    TIMEBASE_FREQUENCY.store(info.timebase_frequency, Ordering::Relaxed);
+    TEST_DEVICE.store(info.test_device.unwrap_or(0), Ordering::Relaxed);
+    *MACHINE.lock() = info;
+    print_machine_info(&info, dtb);
+}
+
+fn print_machine_info(info: &MachineInfo, dtb: usize) {
+    if info.from_dtb {
+        info!("machine: {} (device tree at {:#x})", info.model, dtb);
+    } else {
+        info!("machine: {} (built-in defaults)", info.model);
+    }
+    for &(base, size) in info.memory() {
+        info!("memory  [{:#x}, {:#x}) {} MiB", base, base + size, size >> 20);
+    }
+    info!(
+        "harts   {}, timebase {} Hz",
+        info.harts, info.timebase_frequency
+    );
+    if let Some(uart) = info.uart {
+        info!("uart    {:#x} irq {}", uart.base, uart.irq);
+    }
+    if let Some(plic) = info.plic {
+        info!("plic    [{:#x}, {:#x})", plic.base, plic.base + plic.size);
+    }
+    for device in info.virtio() {
+        info!("virtio  {:#x} irq {}", device.base, device.irq);
+    }
+    if let Some(test) = info.test_device {
+        info!("test    {:#x}", test);
+    }
+    info!("bootargs \"{}\"", info.bootargs);
+}
+
+/// 解析得到的机器信息
+pub fn machine() -> MachineInfo {
+    *MACHINE.lock()
+}
+
+/// time CSR的频率，总是大于0
+pub fn clock_freq() -> usize {
+    TIMEBASE_FREQUENCY.load(Ordering::Relaxed)
+}
+
+/// sifive_test设备的地址，设备树中没有时为None
+pub fn test_device() -> Option<usize> {
+    match TEST_DEVICE.load(Ordering::Relaxed) {
+        0 => None,
+        addr => Some(addr),
+    }
+}
@@ -1,6 +1,10 @@
 //ref:: https://github.com/andre-richter/qemu-exit
 use core::arch::asm;
 
+mod machine;
+
+pub use machine::*;
+
 const EXIT_SUCCESS: u32 = 0x5555; // Equals `exit(0)`. qemu successful exit
 
 const EXIT_FAILURE_FLAG: u32 = 0x3333;
@@ -20,6 +24,9 @@ pub trait QEMUExit {
 
     /// Exit QEMU using `EXIT_FAILURE`, aka `1`.
     fn exit_failure(&self) -> !;
+
+    /// Reset the machine using `EXIT_RESET`, QEMU reloads the images and restarts from the reset vector.
+    fn reset(&self) -> !;
 }
 
 /// RISCV64 configuration
@@ -72,8 +79,59 @@ impl QEMUExit for RISCV64 {
     fn exit_failure(&self) -> ! {
         self.exit(EXIT_FAILURE);
     }
+
+    fn reset(&self) -> ! {
+        self.exit(EXIT_RESET);
+    }
 }
 
-const VIRT_TEST: u64 = 0x100000;
+/// 设备树中sifive_test设备对应的退出句柄
+pub fn qemu_exit_handle() -> Option<RISCV64> {
+    test_device().map(|addr| RISCV64::new(addr as u64))
+}
+
+/// 跨复位保留的启动记录
+///
+/// 位于ekernel之后，不属于内核镜像的任何LOAD段，QEMU复位时重新加载镜像不会覆盖它，
+/// 上电时内存为全0，magic不匹配即视为冷启动
+#[repr(C)]
+struct BootRecord {
+    magic: usize,
+    boots: usize,
+}
 
-pub const QEMU_EXIT_HANDLE: RISCV64 = RISCV64::new(VIRT_TEST);
+const BOOT_RECORD_MAGIC: usize = 0x626f_6f74_5f72_6563; // "boot_rec"
+
+fn boot_record() -> *mut BootRecord {
+    extern "C" {
+        fn sboot_record();
+    }
+    sboot_record as usize as *mut BootRecord
+}
+
+/// 启动记录所在的区域[start, end)，加载app时不能覆盖它
+pub fn boot_record_range() -> (usize, usize) {
+    let start = boot_record() as usize;
+    (start, start + core::mem::size_of::<BootRecord>())
+}
+
+/// 记录一次启动，返回包括本次在内的启动次数
+pub fn record_boot() -> usize {
+    let record = boot_record();
+    unsafe {
+        if record.read_volatile().magic != BOOT_RECORD_MAGIC {
+            record.write_volatile(BootRecord {
+                magic: BOOT_RECORD_MAGIC,
+                boots: 0,
+            });
+        }
+        let boots = (*record).boots + 1;
+        (*record).boots = boots;
+        boots
+    }
+}
+
+/// 包括本次在内的启动次数
+pub fn boot_count() -> usize {
+    unsafe { (*boot_record()).boots }
+}
@@ -1,6 +1,10 @@
 use core::fmt;
 use core::fmt::{Write, Arguments};
-use crate::sbi::console_putchar;
+use crate::drivers::{self, uart};
+use crate::sbi::{console_getchar, console_putchar};
+use crate::smp::{self, hart_id};
+use crate::sync::IrqSafeMutex;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 struct Stdout;
 
@@ -11,18 +15,131 @@ impl Write for Stdout {
         sys_write(1,s.as_bytes());
         Ok(())
     }*/
+    //UART初始化之前(以及设备树中没有UART时)退回到SBI，每个字节一次ecall
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        for c in s.chars() {
-            console_putchar(c as usize);
-        }
+        self.write_bytes(s.as_bytes());
         Ok(())
     }
 }
 
+impl Stdout {
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        match uart::uart() {
+            Some(uart) => bytes.iter().for_each(|&c| uart.putchar(c)),
+            None => bytes.iter().for_each(|&c| console_putchar(c as usize)),
+        }
+    }
+}
+
+//多个hart同时输出时保证一次print的内容不会被打断
+static STDOUT: IrqSafeMutex<Stdout> = IrqSafeMutex::new(Stdout);
+
 pub fn print(args: Arguments) {
-    Stdout.write_fmt(args).unwrap();
+    STDOUT.lock().write_fmt(args).unwrap();
+}
+
+/// 原样输出字节，app写入stdout的内容不一定是UTF-8
+pub fn write_bytes(bytes: &[u8]) {
+    STDOUT.lock().write_bytes(bytes);
+}
+
+const INPUT_BUFFER_SIZE: usize = 256;
+
+/// 控制台输入的环形缓冲区，满了之后丢弃新到达的字节
+struct InputBuffer {
+    buf: [u8; INPUT_BUFFER_SIZE],
+    head: usize,
+    len: usize,
+}
+
+impl InputBuffer {
+    fn push(&mut self, c: u8) {
+        if self.len < INPUT_BUFFER_SIZE {
+            self.buf[(self.head + self.len) % INPUT_BUFFER_SIZE] = c;
+            self.len += 1;
+        }
+    }
+
+    /// 取出最多buf.len()个字节
+    fn pop_into(&mut self, buf: &mut [u8]) -> usize {
+        let count = buf.len().min(self.len);
+        for byte in buf[..count].iter_mut() {
+            *byte = self.buf[self.head];
+            self.head = (self.head + 1) % INPUT_BUFFER_SIZE;
+        }
+        self.len -= count;
+        count
+    }
+}
+
+//UART中断处理程序会写入，必须关中断加锁
+static STDIN: IrqSafeMutex<InputBuffer> = IrqSafeMutex::new(InputBuffer {
+    buf: [0; INPUT_BUFFER_SIZE],
+    head: 0,
+    len: 0,
+});
+
+/// 正在等待输入的hart，每个hart一位
+static INPUT_WAITERS: AtomicUsize = AtomicUsize::new(0);
+
+/// 收到一个输入字节，唤醒其他正在等待输入的hart
+pub fn push_input(c: u8) {
+    STDIN.lock().push(c);
+    let waiters = INPUT_WAITERS.load(Ordering::Acquire) & !(1 << hart_id());
+    if waiters != 0 {
+        smp::send_ipi(waiters);
+    }
+}
+
+/// 没有输入中断时轮询UART或SBI
+fn poll_input() {
+    match uart::uart() {
+        Some(uart) => {
+            while let Some(c) = uart.getchar() {
+                push_input(c);
+            }
+        }
+        None => {
+            //没有数据时legacy console_getchar返回-1
+            let c = console_getchar();
+            if c as isize >= 0 {
+                push_input(c as u8);
+            }
+        }
+    }
+}
+
+/// 读取控制台输入，至少读到一个字节才返回
+///
+/// 内核态不开中断，没有输入时在wfi中等待：本hart的外部中断到来时wfi返回，
+/// 由这里直接处理；中断被其他hart领取时，那个hart通过IPI唤醒这里
+pub fn read_input(buf: &mut [u8]) -> usize {
+    if buf.is_empty() {
+        return 0;
+    }
+    let mask = 1 << hart_id();
+    loop {
+        let count = STDIN.lock().pop_into(buf);
+        if count > 0 {
+            return count;
+        }
+        if !drivers::has_input_interrupt() {
+            poll_input();
+            continue;
+        }
+        INPUT_WAITERS.fetch_or(mask, Ordering::AcqRel);
+        //登记之后再检查一次，避免错过登记之前到达的输入
+        if STDIN.lock().len == 0 {
+            unsafe { riscv::asm::wfi() };
+        }
+        INPUT_WAITERS.fetch_and(!mask, Ordering::AcqRel);
+        smp::clear_ipi();
+        drivers::handle_external_interrupt();
+    }
 }
 
+//app的输出经过write_bytes，内核中暂时没有不换行的输出
+#[allow(unused_macros)]
 macro_rules! print {
     ($fmt: literal $(, $($arg: tt)+)?) => {
         $crate::console::print(format_args!($fmt $(, $($arg)+)?));
@@ -0,0 +1,95 @@
+//! 块设备
+
+mod virtio_blk;
+
+pub use virtio_blk::VirtIOBlock;
+
+use crate::board;
+use crate::drivers::virtio::{DeviceType, MmioTransport};
+use alloc::sync::Arc;
+pub use easy_fs::BlockDevice;
+use lazy_static::*;
+use log::{error, info, warn};
+
+/// 块大小，与virtio-blk的扇区大小以及easy-fs的块大小一致
+pub const BLOCK_SIZE: usize = easy_fs::BLOCK_SZ;
+
+lazy_static! {
+    //第一个virtio-blk设备，设备树中的virtio-mmio槽位按地址从低到高查找
+    static ref VIRTIO_BLOCK: Option<Arc<VirtIOBlock>> = probe().map(Arc::new);
+}
+
+fn probe() -> Option<VirtIOBlock> {
+    let machine = board::machine();
+    let transport = machine
+        .virtio()
+        .iter()
+        .filter_map(|device| MmioTransport::probe(device.base).ok())
+        .find(|transport| transport.device_type() == DeviceType::Block)?;
+    let base = transport.base();
+    match VirtIOBlock::new(transport) {
+        Ok(device) => {
+            info!(
+                "virtio-blk at {:#x} ({}): {} sectors, {} KiB{}",
+                base,
+                if device.is_legacy() { "legacy" } else { "modern" },
+                device.capacity(),
+                device.capacity() * BLOCK_SIZE / 1024,
+                if device.is_read_only() { ", read-only" } else { "" }
+            );
+            Some(device)
+        }
+        Err(err) => {
+            error!("virtio-blk at {:#x} init failed: {:?}", base, err);
+            None
+        }
+    }
+}
+
+/// 查找并初始化块设备，由启动hart调用
+pub fn init() {
+    if VIRTIO_BLOCK.is_none() {
+        warn!("no block device found");
+    }
+}
+
+/// 启动时找到的块设备
+pub fn block_device() -> Option<Arc<dyn BlockDevice>> {
+    VIRTIO_BLOCK
+        .as_ref()
+        .map(|device| Arc::clone(device) as Arc<dyn BlockDevice>)
+}
+
+/// 读写块设备的测试：依次改写几个块再读回比较，最后恢复原来的内容
+///
+/// 被测试的块包括easy-fs的超级块，测试中途断电会损坏文件系统，所以只在打开block_test特性时运行
+#[cfg(feature = "block_test")]
+pub fn block_device_test() {
+    let device = match VIRTIO_BLOCK.as_ref() {
+        Some(device) => device,
+        None => return,
+    };
+    if device.is_read_only() || device.capacity() == 0 {
+        info!("block_device_test skipped: device is read-only or empty");
+        return;
+    }
+    let mut saved = [0u8; BLOCK_SIZE];
+    let mut write_buffer = [0u8; BLOCK_SIZE];
+    let mut read_buffer = [0u8; BLOCK_SIZE];
+    //测试开头和末尾的块
+    let capacity = device.capacity();
+    let blocks = [0, 1, capacity / 2, capacity - 1];
+    for &block_id in blocks.iter().filter(|&&block_id| block_id < capacity) {
+        device.read_block(block_id, &mut saved);
+        for (i, byte) in write_buffer.iter_mut().enumerate() {
+            *byte = (block_id + i) as u8;
+        }
+        device.write_block(block_id, &write_buffer);
+        device.read_block(block_id, &mut read_buffer);
+        assert_eq!(write_buffer, read_buffer, "block {} read back mismatch", block_id);
+        device.write_block(block_id, &saved);
+        device.read_block(block_id, &mut read_buffer);
+        assert_eq!(saved, read_buffer, "block {} restore mismatch", block_id);
+    }
+    info!("block_device_test passed!");
+}
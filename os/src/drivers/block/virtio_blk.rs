@@ -0,0 +1,145 @@
+//! virtio-blk驱动
+//!
+//! 每个请求由三个描述符组成：请求头、数据缓冲区和设备写回的状态字节。
+//! 请求同步完成：提交后轮询used ring，直到设备处理完成
+
+use super::{BlockDevice, BLOCK_SIZE};
+use crate::drivers::virtio::{Descriptor, MmioTransport, VirtIOError, VirtQueue, VirtQueueLayout, DESC_F_WRITE};
+use crate::sync::SpinMutex;
+use core::cell::UnsafeCell;
+
+const VIRTIO_BLK_T_IN: u32 = 0;
+const VIRTIO_BLK_T_OUT: u32 = 1;
+const VIRTIO_BLK_S_OK: u8 = 0;
+
+/// 设备只读
+const VIRTIO_BLK_F_RO: u64 = 1 << 5;
+
+/// 配置空间中capacity(以512字节扇区为单位)的偏移
+const CONFIG_CAPACITY: usize = 0;
+
+#[repr(C)]
+struct BlkReqHeader {
+    req_type: u32,
+    reserved: u32,
+    sector: u64,
+}
+
+/// 设备会通过DMA访问的内存，只在持有VirtIOBlock的锁时访问
+struct DmaArea {
+    queue: UnsafeCell<VirtQueueLayout>,
+    header: UnsafeCell<BlkReqHeader>,
+    status: UnsafeCell<u8>,
+}
+
+unsafe impl Sync for DmaArea {}
+
+static DMA: DmaArea = DmaArea {
+    queue: UnsafeCell::new(VirtQueueLayout::new()),
+    header: UnsafeCell::new(BlkReqHeader {
+        req_type: 0,
+        reserved: 0,
+        sector: 0,
+    }),
+    status: UnsafeCell::new(0),
+};
+
+struct VirtIOBlockInner {
+    transport: MmioTransport,
+    queue: VirtQueue,
+}
+
+pub struct VirtIOBlock {
+    inner: SpinMutex<VirtIOBlockInner>,
+    capacity: usize,
+    read_only: bool,
+}
+
+impl VirtIOBlock {
+    /// 初始化transport上的virtio-blk设备，DMA内存是静态的，只能调用一次
+    pub fn new(transport: MmioTransport) -> Result<Self, VirtIOError> {
+        let features = transport.begin_init(VIRTIO_BLK_F_RO)?;
+        let queue = transport.setup_queue(0, unsafe { &mut *DMA.queue.get() })?;
+        transport.finish_init();
+        let capacity = transport.config_u32(CONFIG_CAPACITY) as usize
+            | (transport.config_u32(CONFIG_CAPACITY + 4) as usize) << 32;
+        Ok(Self {
+            inner: SpinMutex::new(VirtIOBlockInner { transport, queue }),
+            capacity,
+            read_only: features & VIRTIO_BLK_F_RO != 0,
+        })
+    }
+
+    /// 以512字节扇区为单位的容量
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        self.inner.lock().transport.is_legacy()
+    }
+
+    /// 执行一次请求，buf为数据缓冲区，读请求时由设备写入
+    fn request(&self, req_type: u32, block_id: usize, buf: *mut u8) {
+        assert!(
+            block_id < self.capacity,
+            "block {} out of range (capacity {})",
+            block_id,
+            self.capacity
+        );
+        let mut inner = self.inner.lock();
+        let inner = &mut *inner;
+        let (header, status) = (DMA.header.get(), DMA.status.get());
+        unsafe {
+            header.write_volatile(BlkReqHeader {
+                req_type,
+                reserved: 0,
+                sector: block_id as u64,
+            });
+            status.write_volatile(0xff);
+        }
+        let data_flags = if req_type == VIRTIO_BLK_T_IN { DESC_F_WRITE } else { 0 };
+        inner.queue.submit_and_wait(
+            &inner.transport,
+            &[
+                Descriptor {
+                    addr: header as u64,
+                    len: core::mem::size_of::<BlkReqHeader>() as u32,
+                    flags: 0,
+                    next: 0,
+                },
+                Descriptor {
+                    addr: buf as u64,
+                    len: BLOCK_SIZE as u32,
+                    flags: data_flags,
+                    next: 0,
+                },
+                Descriptor {
+                    addr: status as u64,
+                    len: 1,
+                    flags: DESC_F_WRITE,
+                    next: 0,
+                },
+            ],
+        );
+        let status = unsafe { status.read_volatile() };
+        assert_eq!(status, VIRTIO_BLK_S_OK, "virtio-blk request on block {} failed", block_id);
+    }
+}
+
+impl BlockDevice for VirtIOBlock {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        assert_eq!(buf.len(), BLOCK_SIZE);
+        self.request(VIRTIO_BLK_T_IN, block_id, buf.as_mut_ptr());
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        assert_eq!(buf.len(), BLOCK_SIZE);
+        assert!(!self.read_only, "write to read-only virtio-blk device");
+        self.request(VIRTIO_BLK_T_OUT, block_id, buf.as_ptr() as *mut u8);
+    }
+}
@@ -0,0 +1,75 @@
+//! 设备驱动
+//!
+//! 设备地址都来自设备树，在`board::init`之后由启动hart调用`init`初始化，
+//! 每个hart再调用`init_hart`在自己的PLIC context中使能设备中断
+
+pub mod block;
+pub mod plic;
+pub mod uart;
+pub mod virtio;
+
+use crate::board;
+use crate::smp::hart_id;
+use core::sync::atomic::{AtomicU32, Ordering};
+use log::{info, warn};
+
+/// UART的中断号，为0时UART不使用中断
+static UART_IRQ: AtomicU32 = AtomicU32::new(0);
+
+pub fn init() {
+    let machine = board::machine();
+    if let Some(device) = machine.uart {
+        uart::init(device.base);
+        info!("console switched to ns16550a uart at {:#x}", device.base);
+    }
+    if let Some(device) = machine.plic {
+        plic::init(device.base);
+        if let (Some(uart), Some(plic)) = (uart::uart(), plic::plic()) {
+            let irq = machine.uart.map_or(0, |device| device.irq);
+            if irq != 0 {
+                plic.set_priority(irq, 1);
+                uart.enable_receive_interrupt();
+                UART_IRQ.store(irq, Ordering::Release);
+            }
+        }
+    }
+    block::init();
+    #[cfg(feature = "block_test")]
+    block::block_device_test();
+}
+
+/// 在当前hart的S态context中使能设备中断
+pub fn init_hart() {
+    if let Some(plic) = plic::plic() {
+        let context = plic::supervisor_context(hart_id());
+        plic.set_threshold(context, 0);
+        let irq = UART_IRQ.load(Ordering::Acquire);
+        if irq != 0 {
+            plic.enable(context, irq);
+        }
+    }
+}
+
+/// 控制台输入是否由中断驱动，否则读取时需要轮询
+pub fn has_input_interrupt() -> bool {
+    UART_IRQ.load(Ordering::Acquire) != 0
+}
+
+/// 处理当前hart上所有待处理的外部中断
+///
+/// 同一个中断会送到所有使能它的hart，只有claim成功的hart需要处理
+pub fn handle_external_interrupt() {
+    let plic = match plic::plic() {
+        Some(plic) => plic,
+        None => return,
+    };
+    let context = plic::supervisor_context(hart_id());
+    while let Some(irq) = plic.claim(context) {
+        if irq == UART_IRQ.load(Ordering::Acquire) {
+            uart::handle_interrupt();
+        } else {
+            warn!("unexpected external interrupt {}", irq);
+        }
+        plic.complete(context, irq);
+    }
+}
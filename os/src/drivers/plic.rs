@@ -0,0 +1,90 @@
+//! PLIC (Platform-Level Interrupt Controller)驱动
+//!
+//! 每个hart的每个特权级对应一个context，QEMU virt上hart i的M态为context 2i，S态为context 2i+1。
+//! 中断源优先级大于context的阈值且在该context中被使能时，中断会送到对应的hart，
+//! 由hart claim得到中断号，处理完后写回complete
+//!
+//! reference: https://github.com/riscv/riscv-plic-spec/blob/master/riscv-plic.adoc
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+const PRIORITY_BASE: usize = 0x0;
+const ENABLE_BASE: usize = 0x2000;
+const ENABLE_STRIDE: usize = 0x80;
+const CONTEXT_BASE: usize = 0x20_0000;
+const CONTEXT_STRIDE: usize = 0x1000;
+const CONTEXT_THRESHOLD: usize = 0x0;
+const CONTEXT_CLAIM: usize = 0x4;
+
+/// PLIC的基地址，为0时尚未初始化
+static PLIC_BASE: AtomicUsize = AtomicUsize::new(0);
+
+pub struct Plic {
+    base: usize,
+}
+
+impl Plic {
+    pub const fn new(base: usize) -> Self {
+        Self { base }
+    }
+
+    fn reg(&self, offset: usize) -> *mut u32 {
+        (self.base + offset) as *mut u32
+    }
+
+    /// 设置中断源的优先级，0表示永不触发
+    pub fn set_priority(&self, irq: u32, priority: u32) {
+        unsafe { self.reg(PRIORITY_BASE + irq as usize * 4).write_volatile(priority) }
+    }
+
+    /// 在context中使能中断源
+    pub fn enable(&self, context: usize, irq: u32) {
+        let reg = self.reg(ENABLE_BASE + context * ENABLE_STRIDE + irq as usize / 32 * 4);
+        unsafe { reg.write_volatile(reg.read_volatile() | 1 << (irq % 32)) }
+    }
+
+    /// 只有优先级大于阈值的中断才会送到context
+    pub fn set_threshold(&self, context: usize, threshold: u32) {
+        unsafe {
+            self.reg(CONTEXT_BASE + context * CONTEXT_STRIDE + CONTEXT_THRESHOLD)
+                .write_volatile(threshold)
+        }
+    }
+
+    /// 领取一个待处理的中断，没有时返回None
+    pub fn claim(&self, context: usize) -> Option<u32> {
+        let irq = unsafe {
+            self.reg(CONTEXT_BASE + context * CONTEXT_STRIDE + CONTEXT_CLAIM)
+                .read_volatile()
+        };
+        match irq {
+            0 => None,
+            irq => Some(irq),
+        }
+    }
+
+    /// 通知PLIC中断处理完毕，此后同一中断源才能再次触发
+    pub fn complete(&self, context: usize, irq: u32) {
+        unsafe {
+            self.reg(CONTEXT_BASE + context * CONTEXT_STRIDE + CONTEXT_CLAIM)
+                .write_volatile(irq)
+        }
+    }
+}
+
+/// hart在S态下的context
+pub fn supervisor_context(hart_id: usize) -> usize {
+    hart_id * 2 + 1
+}
+
+pub fn init(base: usize) {
+    PLIC_BASE.store(base, Ordering::Release);
+}
+
+/// 已经初始化的PLIC
+pub fn plic() -> Option<Plic> {
+    match PLIC_BASE.load(Ordering::Acquire) {
+        0 => None,
+        base => Some(Plic::new(base)),
+    }
+}
@@ -0,0 +1,110 @@
+//! NS16550A UART驱动
+//!
+//! QEMU virt的UART寄存器间隔为1字节(reg-shift = 0)。发送使用轮询方式，
+//! 接收在PLIC可用时使用中断，收到的字节交给`console`的输入缓冲区。
+//! init之前`console`仍然通过SBI输出
+//!
+//! reference: http://byterunner.com/16550.html
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// 接收缓冲寄存器(读)/发送保持寄存器(写)，DLAB=1时为除数低8位
+const RBR_THR_DLL: usize = 0;
+/// 中断使能寄存器，DLAB=1时为除数高8位
+const IER_DLM: usize = 1;
+/// FIFO控制寄存器(写)
+const FCR: usize = 2;
+/// 线路控制寄存器
+const LCR: usize = 3;
+/// Modem控制寄存器
+const MCR: usize = 4;
+/// 线路状态寄存器
+const LSR: usize = 5;
+
+const IER_RX_AVAILABLE: u8 = 1 << 0;
+const LCR_8N1: u8 = 0b11;
+const LCR_DLAB: u8 = 1 << 7;
+const FCR_ENABLE_AND_CLEAR: u8 = 0b111;
+const MCR_DTR_RTS_OUT2: u8 = 0b1011;
+const LSR_DATA_READY: u8 = 1 << 0;
+const LSR_THR_EMPTY: u8 = 1 << 5;
+
+/// QEMU virt的UART输入时钟为3.6864MHz，除数1对应230400波特率，QEMU实际上忽略这个设置
+const DIVISOR: u16 = 1;
+
+/// UART的基地址，为0时尚未初始化
+static UART_BASE: AtomicUsize = AtomicUsize::new(0);
+
+pub struct Ns16550a {
+    base: usize,
+}
+
+impl Ns16550a {
+    pub const fn new(base: usize) -> Self {
+        Self { base }
+    }
+
+    fn read(&self, reg: usize) -> u8 {
+        unsafe { ((self.base + reg) as *const u8).read_volatile() }
+    }
+
+    fn write(&self, reg: usize, val: u8) {
+        unsafe { ((self.base + reg) as *mut u8).write_volatile(val) }
+    }
+
+    /// 8N1，打开FIFO，关闭所有中断
+    pub fn init(&self) {
+        self.write(IER_DLM, 0);
+        self.write(LCR, LCR_DLAB);
+        self.write(RBR_THR_DLL, DIVISOR as u8);
+        self.write(IER_DLM, (DIVISOR >> 8) as u8);
+        self.write(LCR, LCR_8N1);
+        self.write(FCR, FCR_ENABLE_AND_CLEAR);
+        self.write(MCR, MCR_DTR_RTS_OUT2);
+    }
+
+    /// 等待发送保持寄存器为空后发送一个字节
+    pub fn putchar(&self, c: u8) {
+        while self.read(LSR) & LSR_THR_EMPTY == 0 {
+            core::hint::spin_loop();
+        }
+        self.write(RBR_THR_DLL, c);
+    }
+
+    /// 收到数据时触发中断
+    pub fn enable_receive_interrupt(&self) {
+        self.write(IER_DLM, IER_RX_AVAILABLE);
+    }
+
+    /// 读取一个字节，没有数据时返回None
+    pub fn getchar(&self) -> Option<u8> {
+        if self.read(LSR) & LSR_DATA_READY != 0 {
+            Some(self.read(RBR_THR_DLL))
+        } else {
+            None
+        }
+    }
+}
+
+/// 初始化位于base的UART，此后控制台输出改为直接写UART
+pub fn init(base: usize) {
+    Ns16550a::new(base).init();
+    UART_BASE.store(base, Ordering::Release);
+}
+
+/// 已经初始化的UART
+pub fn uart() -> Option<Ns16550a> {
+    match UART_BASE.load(Ordering::Acquire) {
+        0 => None,
+        base => Some(Ns16550a::new(base)),
+    }
+}
+
+/// UART接收中断，把FIFO中的数据全部读入控制台的输入缓冲区
+pub fn handle_interrupt() {
+    if let Some(uart) = uart() {
+        while let Some(c) = uart.getchar() {
+            crate::console::push_input(c);
+        }
+    }
+}
@@ -0,0 +1,340 @@
+//! virtio-mmio传输层
+//!
+//! 同时支持legacy(Version 1)和modern(Version 2)设备：两者寄存器布局基本相同，
+//! 区别在于legacy用QueuePFN给出整个virtqueue所在的页，modern分别给出三个部分的地址，
+//! 并且modern必须协商VIRTIO_F_VERSION_1。QEMU默认提供legacy设备，
+//! 加上`-global virtio-mmio.force-legacy=false`后为modern设备。没有开启分页，虚拟地址即物理地址，可以直接交给设备
+//!
+//! reference: https://docs.oasis-open.org/virtio/virtio/v1.1/virtio-v1.1.html
+
+use core::ptr::{addr_of, addr_of_mut};
+use core::sync::atomic::{fence, Ordering};
+
+const MAGIC_VALUE: usize = 0x000;
+const VERSION: usize = 0x004;
+const DEVICE_ID: usize = 0x008;
+const DEVICE_FEATURES: usize = 0x010;
+const DEVICE_FEATURES_SEL: usize = 0x014;
+const DRIVER_FEATURES: usize = 0x020;
+const DRIVER_FEATURES_SEL: usize = 0x024;
+const GUEST_PAGE_SIZE: usize = 0x028;
+const QUEUE_SEL: usize = 0x030;
+const QUEUE_NUM_MAX: usize = 0x034;
+const QUEUE_NUM: usize = 0x038;
+const QUEUE_ALIGN: usize = 0x03c;
+const QUEUE_PFN: usize = 0x040;
+const QUEUE_READY: usize = 0x044;
+const QUEUE_NOTIFY: usize = 0x050;
+const INTERRUPT_STATUS: usize = 0x060;
+const INTERRUPT_ACK: usize = 0x064;
+const STATUS: usize = 0x070;
+const QUEUE_DESC_LOW: usize = 0x080;
+const QUEUE_DESC_HIGH: usize = 0x084;
+const QUEUE_DRIVER_LOW: usize = 0x090;
+const QUEUE_DRIVER_HIGH: usize = 0x094;
+const QUEUE_DEVICE_LOW: usize = 0x0a0;
+const QUEUE_DEVICE_HIGH: usize = 0x0a4;
+const CONFIG: usize = 0x100;
+
+/// "virt"
+const VIRTIO_MAGIC: u32 = 0x7472_6976;
+
+const STATUS_ACKNOWLEDGE: u32 = 1;
+const STATUS_DRIVER: u32 = 2;
+const STATUS_DRIVER_OK: u32 = 4;
+const STATUS_FEATURES_OK: u32 = 8;
+const STATUS_FAILED: u32 = 128;
+
+/// modern设备必须协商的特性位
+const VIRTIO_F_VERSION_1: u64 = 1 << 32;
+
+const PAGE_SIZE: usize = 4096;
+
+/// 每个virtqueue的描述符数量，驱动每次只提交一个请求，不需要太多
+pub const QUEUE_SIZE: usize = 16;
+
+pub const DESC_F_NEXT: u16 = 1;
+/// 设备写入的缓冲区
+pub const DESC_F_WRITE: u16 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceType {
+    Network = 1,
+    Block = 2,
+    Console = 3,
+    EntropySource = 4,
+    Unknown,
+}
+
+impl From<u32> for DeviceType {
+    fn from(id: u32) -> Self {
+        match id {
+            1 => DeviceType::Network,
+            2 => DeviceType::Block,
+            3 => DeviceType::Console,
+            4 => DeviceType::EntropySource,
+            _ => DeviceType::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VirtIOError {
+    /// 不是virtio设备，或者是没有接设备的空槽位(DeviceID为0)
+    NotPresent,
+    UnsupportedVersion(u32),
+    FeaturesRejected,
+    QueueUnavailable,
+    QueueTooSmall(u32),
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Descriptor {
+    pub addr: u64,
+    pub len: u32,
+    pub flags: u16,
+    pub next: u16,
+}
+
+#[repr(C)]
+struct AvailRing {
+    flags: u16,
+    idx: u16,
+    ring: [u16; QUEUE_SIZE],
+    used_event: u16,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct UsedElem {
+    id: u32,
+    len: u32,
+}
+
+/// legacy设备要求used ring从QueueAlign对齐的位置开始
+#[repr(C, align(4096))]
+struct UsedRing {
+    flags: u16,
+    idx: u16,
+    ring: [UsedElem; QUEUE_SIZE],
+    avail_event: u16,
+}
+
+/// legacy设备要求的split virtqueue内存布局：描述符表、avail ring，
+/// 然后在下一个页上放used ring。modern设备也使用同样的布局
+#[repr(C, align(4096))]
+pub struct VirtQueueLayout {
+    desc: [Descriptor; QUEUE_SIZE],
+    avail: AvailRing,
+    used: UsedRing,
+}
+
+impl VirtQueueLayout {
+    pub const fn new() -> Self {
+        const EMPTY_DESC: Descriptor = Descriptor {
+            addr: 0,
+            len: 0,
+            flags: 0,
+            next: 0,
+        };
+        Self {
+            desc: [EMPTY_DESC; QUEUE_SIZE],
+            avail: AvailRing {
+                flags: 0,
+                idx: 0,
+                ring: [0; QUEUE_SIZE],
+                used_event: 0,
+            },
+            used: UsedRing {
+                flags: 0,
+                idx: 0,
+                ring: [UsedElem { id: 0, len: 0 }; QUEUE_SIZE],
+                avail_event: 0,
+            },
+        }
+    }
+}
+
+/// 一个split virtqueue，内存由调用者提供并且必须一直有效
+pub struct VirtQueue {
+    layout: *mut VirtQueueLayout,
+    /// 上一次看到的used.idx
+    last_used: u16,
+}
+
+// 队列内存是调用者提供的静态内存，只在持有设备锁时访问
+unsafe impl Send for VirtQueue {}
+
+impl VirtQueue {
+    /// 提交由descriptors组成的一个描述符链，阻塞直到设备处理完成，返回设备写入的字节数
+    pub fn submit_and_wait(&mut self, transport: &MmioTransport, descriptors: &[Descriptor]) -> u32 {
+        assert!(!descriptors.is_empty() && descriptors.len() <= QUEUE_SIZE);
+        let layout = self.layout;
+        unsafe {
+            //每次都从0号描述符开始依次链接
+            for (i, desc) in descriptors.iter().enumerate() {
+                let mut desc = *desc;
+                if i + 1 < descriptors.len() {
+                    desc.flags |= DESC_F_NEXT;
+                    desc.next = i as u16 + 1;
+                }
+                addr_of_mut!((*layout).desc[i]).write_volatile(desc);
+            }
+            let avail_idx = addr_of!((*layout).avail.idx).read_volatile();
+            addr_of_mut!((*layout).avail.ring[avail_idx as usize % QUEUE_SIZE]).write_volatile(0);
+            //描述符必须在avail.idx之前对设备可见
+            fence(Ordering::SeqCst);
+            addr_of_mut!((*layout).avail.idx).write_volatile(avail_idx.wrapping_add(1));
+            fence(Ordering::SeqCst);
+            transport.notify(0);
+            while addr_of!((*layout).used.idx).read_volatile() == self.last_used {
+                core::hint::spin_loop();
+            }
+            fence(Ordering::SeqCst);
+            let elem = addr_of!((*layout).used.ring[self.last_used as usize % QUEUE_SIZE]).read_volatile();
+            self.last_used = self.last_used.wrapping_add(1);
+            transport.ack_interrupt();
+            elem.len
+        }
+    }
+}
+
+/// virtio-mmio设备的寄存器
+pub struct MmioTransport {
+    base: usize,
+    version: u32,
+}
+
+impl MmioTransport {
+    /// 检查base处是否有virtio设备
+    pub fn probe(base: usize) -> Result<Self, VirtIOError> {
+        let transport = Self { base, version: 0 };
+        if transport.read(MAGIC_VALUE) != VIRTIO_MAGIC || transport.read(DEVICE_ID) == 0 {
+            return Err(VirtIOError::NotPresent);
+        }
+        let version = transport.read(VERSION);
+        if version != 1 && version != 2 {
+            return Err(VirtIOError::UnsupportedVersion(version));
+        }
+        Ok(Self { base, version })
+    }
+
+    fn read(&self, offset: usize) -> u32 {
+        unsafe { ((self.base + offset) as *const u32).read_volatile() }
+    }
+
+    fn write(&self, offset: usize, val: u32) {
+        unsafe { ((self.base + offset) as *mut u32).write_volatile(val) }
+    }
+
+    pub fn device_type(&self) -> DeviceType {
+        DeviceType::from(self.read(DEVICE_ID))
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        self.version == 1
+    }
+
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    /// 复位设备并协商特性，driver_features为驱动支持的设备相关特性，返回最终协商的结果
+    pub fn begin_init(&self, driver_features: u64) -> Result<u64, VirtIOError> {
+        self.write(STATUS, 0);
+        self.write(STATUS, STATUS_ACKNOWLEDGE);
+        self.write(STATUS, STATUS_ACKNOWLEDGE | STATUS_DRIVER);
+
+        self.write(DEVICE_FEATURES_SEL, 0);
+        let mut device_features = self.read(DEVICE_FEATURES) as u64;
+        if !self.is_legacy() {
+            self.write(DEVICE_FEATURES_SEL, 1);
+            device_features |= (self.read(DEVICE_FEATURES) as u64) << 32;
+        }
+        let mut features = device_features & driver_features;
+        if !self.is_legacy() {
+            if device_features & VIRTIO_F_VERSION_1 == 0 {
+                self.write(STATUS, STATUS_FAILED);
+                return Err(VirtIOError::FeaturesRejected);
+            }
+            features |= VIRTIO_F_VERSION_1;
+        }
+        self.write(DRIVER_FEATURES_SEL, 0);
+        self.write(DRIVER_FEATURES, features as u32);
+        if !self.is_legacy() {
+            self.write(DRIVER_FEATURES_SEL, 1);
+            self.write(DRIVER_FEATURES, (features >> 32) as u32);
+            //legacy设备没有FEATURES_OK这一步
+            self.write(STATUS, STATUS_ACKNOWLEDGE | STATUS_DRIVER | STATUS_FEATURES_OK);
+            if self.read(STATUS) & STATUS_FEATURES_OK == 0 {
+                self.write(STATUS, STATUS_FAILED);
+                return Err(VirtIOError::FeaturesRejected);
+            }
+        }
+        Ok(features)
+    }
+
+    /// 把layout设置为第queue个virtqueue
+    pub fn setup_queue(&self, queue: u32, layout: &'static mut VirtQueueLayout) -> Result<VirtQueue, VirtIOError> {
+        self.write(QUEUE_SEL, queue);
+        if !self.is_legacy() && self.read(QUEUE_READY) != 0 {
+            return Err(VirtIOError::QueueUnavailable);
+        }
+        let max = self.read(QUEUE_NUM_MAX);
+        if max == 0 {
+            return Err(VirtIOError::QueueUnavailable);
+        }
+        if (max as usize) < QUEUE_SIZE {
+            return Err(VirtIOError::QueueTooSmall(max));
+        }
+        *layout = VirtQueueLayout::new();
+        let layout = layout as *mut VirtQueueLayout;
+        self.write(QUEUE_NUM, QUEUE_SIZE as u32);
+        if self.is_legacy() {
+            self.write(GUEST_PAGE_SIZE, PAGE_SIZE as u32);
+            self.write(QUEUE_ALIGN, PAGE_SIZE as u32);
+            self.write(QUEUE_PFN, (layout as usize / PAGE_SIZE) as u32);
+        } else {
+            let (desc, driver, device) = unsafe {
+                (
+                    addr_of!((*layout).desc) as u64,
+                    addr_of!((*layout).avail) as u64,
+                    addr_of!((*layout).used) as u64,
+                )
+            };
+            self.write(QUEUE_DESC_LOW, desc as u32);
+            self.write(QUEUE_DESC_HIGH, (desc >> 32) as u32);
+            self.write(QUEUE_DRIVER_LOW, driver as u32);
+            self.write(QUEUE_DRIVER_HIGH, (driver >> 32) as u32);
+            self.write(QUEUE_DEVICE_LOW, device as u32);
+            self.write(QUEUE_DEVICE_HIGH, (device >> 32) as u32);
+            self.write(QUEUE_READY, 1);
+        }
+        Ok(VirtQueue {
+            layout,
+            last_used: 0,
+        })
+    }
+
+    /// 设备初始化完成
+    pub fn finish_init(&self) {
+        let status = self.read(STATUS);
+        self.write(STATUS, status | STATUS_DRIVER_OK);
+    }
+
+    pub fn notify(&self, queue: u32) {
+        self.write(QUEUE_NOTIFY, queue);
+    }
+
+    /// 应答设备中断，驱动使用轮询，但中断状态仍需清除
+    pub fn ack_interrupt(&self) {
+        let status = self.read(INTERRUPT_STATUS);
+        self.write(INTERRUPT_ACK, status);
+    }
+
+    /// 读取设备配置空间中offset处的u32
+    pub fn config_u32(&self, offset: usize) -> u32 {
+        self.read(CONFIG + offset)
+    }
+}
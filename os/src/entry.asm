@@ -1,12 +1,26 @@
     .section .text.entry
     .globl _start
 _start:
+    # a0 = hartid, a1 = DTB地址, 启动hart和通过HSM启动的其他hart都从这里进入
+    # 超出smp::MAX_HARTS的hart没有启动栈，直接停在这里，max_harts由main.rs中的global_asm!传入
+    li t0, {max_harts}
+    bgeu a0, t0, 1f
+    # tp在内核中始终保存当前hart的id
+    mv tp, a0
+    # 每个hart使用自己的64K启动栈: sp = boot_stack_top - (hartid << 16)
     la sp, boot_stack_top
+    slli t0, a0, 16
+    sub sp, sp, t0
+    # a0, a1原样作为rust_main的参数
     call rust_main
+1:
+    wfi
+    j 1b
 
     .section .bss.stack
     .globl boot_stack
 boot_stack:
-    .space 4096 * 16
+    # 每个hart 64K，与上面的移位一致
+    .space 4096 * 16 * {max_harts}
     .globl boot_stack_top
 boot_stack_top:
@@ -0,0 +1,227 @@
+//! Flattened Device Tree parser
+//!
+//! SBI在跳转到内核时通过a1传入DTB的物理地址。这里只实现遍历结构块的最小解析器，
+//! 不需要堆分配：每个节点的所有属性都出现在它的子节点之前，
+//! 因此在节点结束(FDT_END_NODE)时它的属性已经全部读到，此时交给回调处理。
+//!
+//! reference: https://devicetree-specification.readthedocs.io/en/stable/flattened-format.html
+
+const FDT_MAGIC: u32 = 0xd00d_feed;
+
+const FDT_BEGIN_NODE: u32 = 0x1;
+const FDT_END_NODE: u32 = 0x2;
+const FDT_PROP: u32 = 0x3;
+const FDT_NOP: u32 = 0x4;
+const FDT_END: u32 = 0x9;
+
+/// 支持的最大节点深度
+const MAX_DEPTH: usize = 8;
+/// 每个节点最多记录的属性数量，多出的属性被忽略
+const MAX_PROPS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FdtError {
+    BadMagic,
+    Truncated,
+    BadToken(u32),
+    TooDeep,
+}
+
+/// 一个解析好的DTB
+pub struct Fdt<'a> {
+    structs: &'a [u8],
+    strings: &'a [u8],
+}
+
+/// 遍历时交给回调的节点
+pub struct Node<'a> {
+    /// 节点名，包括unit address，如`uart@10000000`
+    pub name: &'a str,
+    /// 根节点深度为0
+    pub depth: usize,
+    props: [(&'a str, &'a [u8]); MAX_PROPS],
+    prop_count: usize,
+    /// 父节点的#address-cells和#size-cells，用于解析本节点的reg
+    address_cells: usize,
+    size_cells: usize,
+}
+
+fn be_u32(data: &[u8], offset: usize) -> Result<u32, FdtError> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or(FdtError::Truncated)
+}
+
+/// 读取以NUL结尾的字符串
+fn c_str(data: &[u8], offset: usize) -> Result<&str, FdtError> {
+    let bytes = data.get(offset..).ok_or(FdtError::Truncated)?;
+    let len = bytes.iter().position(|&b| b == 0).ok_or(FdtError::Truncated)?;
+    core::str::from_utf8(&bytes[..len]).map_err(|_| FdtError::Truncated)
+}
+
+/// 把cells个大端u32拼成一个数
+fn read_cells(data: &[u8], cells: usize) -> usize {
+    data.chunks_exact(4)
+        .take(cells)
+        .fold(0, |acc, chunk| (acc << 32) | u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) as usize)
+}
+
+impl<'a> Fdt<'a> {
+    /// 从内存中的DTB构造，头部中的totalsize决定DTB的长度
+    ///
+    /// # Safety
+    /// addr必须指向一个有效的DTB，并且在'a期间不会被修改
+    pub unsafe fn from_addr(addr: usize) -> Result<Self, FdtError> {
+        let header = core::slice::from_raw_parts(addr as *const u8, 40);
+        if be_u32(header, 0)? != FDT_MAGIC {
+            return Err(FdtError::BadMagic);
+        }
+        let total_size = be_u32(header, 4)? as usize;
+        Self::from_bytes(core::slice::from_raw_parts(addr as *const u8, total_size))
+    }
+
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, FdtError> {
+        if be_u32(data, 0)? != FDT_MAGIC {
+            return Err(FdtError::BadMagic);
+        }
+        let off_struct = be_u32(data, 8)? as usize;
+        let off_strings = be_u32(data, 12)? as usize;
+        let size_strings = be_u32(data, 32)? as usize;
+        let size_struct = be_u32(data, 36)? as usize;
+        Ok(Self {
+            structs: data.get(off_struct..off_struct + size_struct).ok_or(FdtError::Truncated)?,
+            strings: data.get(off_strings..off_strings + size_strings).ok_or(FdtError::Truncated)?,
+        })
+    }
+
+    /// 深度优先遍历所有节点，每个节点在结束时调用一次f，因此子节点先于父节点被处理
+    pub fn for_each_node(&self, mut f: impl FnMut(&Node<'a>)) -> Result<(), FdtError> {
+        const EMPTY: Node<'static> = Node {
+            name: "",
+            depth: 0,
+            props: [("", &[]); MAX_PROPS],
+            prop_count: 0,
+            address_cells: 2,
+            size_cells: 1,
+        };
+        let mut stack = [EMPTY; MAX_DEPTH];
+        let mut depth = 0;
+        let mut offset = 0;
+        loop {
+            let token = be_u32(self.structs, offset)?;
+            offset += 4;
+            match token {
+                FDT_BEGIN_NODE => {
+                    if depth == MAX_DEPTH {
+                        return Err(FdtError::TooDeep);
+                    }
+                    let name = c_str(self.structs, offset)?;
+                    offset = (offset + name.len() + 1 + 3) & !3;
+                    //规范规定缺省的#address-cells为2，#size-cells为1
+                    let (address_cells, size_cells) = match depth {
+                        0 => (2, 1),
+                        _ => {
+                            let parent = &stack[depth - 1];
+                            (
+                                parent.prop_u32("#address-cells").unwrap_or(2) as usize,
+                                parent.prop_u32("#size-cells").unwrap_or(1) as usize,
+                            )
+                        }
+                    };
+                    stack[depth] = Node {
+                        name,
+                        depth,
+                        address_cells,
+                        size_cells,
+                        ..EMPTY
+                    };
+                    depth += 1;
+                }
+                FDT_END_NODE => {
+                    if depth == 0 {
+                        return Err(FdtError::BadToken(token));
+                    }
+                    depth -= 1;
+                    f(&stack[depth]);
+                }
+                FDT_PROP => {
+                    let len = be_u32(self.structs, offset)? as usize;
+                    let name_off = be_u32(self.structs, offset + 4)? as usize;
+                    let value = self
+                        .structs
+                        .get(offset + 8..offset + 8 + len)
+                        .ok_or(FdtError::Truncated)?;
+                    offset = (offset + 8 + len + 3) & !3;
+                    let name = c_str(self.strings, name_off)?;
+                    if depth > 0 {
+                        let node = &mut stack[depth - 1];
+                        if node.prop_count < MAX_PROPS {
+                            node.props[node.prop_count] = (name, value);
+                            node.prop_count += 1;
+                        }
+                    }
+                }
+                FDT_NOP => {}
+                FDT_END => return Ok(()),
+                _ => return Err(FdtError::BadToken(token)),
+            }
+        }
+    }
+}
+
+impl<'a> Node<'a> {
+    pub fn prop(&self, name: &str) -> Option<&'a [u8]> {
+        self.props[..self.prop_count]
+            .iter()
+            .find(|(prop, _)| *prop == name)
+            .map(|&(_, value)| value)
+    }
+
+    pub fn prop_u32(&self, name: &str) -> Option<u32> {
+        self.prop(name).and_then(|value| be_u32(value, 0).ok())
+    }
+
+    /// 把属性读作一个cells个u32组成的数，timebase-frequency等属性可能是1或2个cell
+    pub fn prop_usize(&self, name: &str) -> Option<usize> {
+        self.prop(name).map(|value| read_cells(value, value.len() / 4))
+    }
+
+    /// 读取字符串属性(去掉结尾的NUL)
+    pub fn prop_str(&self, name: &str) -> Option<&'a str> {
+        self.prop(name).and_then(|value| c_str(value, 0).ok())
+    }
+
+    /// compatible属性是否包含compat
+    pub fn is_compatible(&self, compat: &str) -> bool {
+        self.prop("compatible").map_or(false, |value| {
+            value
+                .split(|&b| b == 0)
+                .any(|item| item == compat.as_bytes())
+        })
+    }
+
+    /// 节点名去掉unit address的部分
+    pub fn base_name(&self) -> &'a str {
+        self.name.split('@').next().unwrap_or(self.name)
+    }
+
+    /// 遍历reg属性中的(地址, 大小)
+    pub fn reg(&self) -> impl Iterator<Item = (usize, usize)> + 'a {
+        let (address_cells, size_cells) = (self.address_cells, self.size_cells);
+        let entry = (address_cells + size_cells) * 4;
+        self.prop("reg")
+            .unwrap_or(&[])
+            .chunks_exact(entry.max(4))
+            .map(move |chunk| {
+                (
+                    read_cells(chunk, address_cells),
+                    read_cells(&chunk[address_cells * 4..], size_cells),
+                )
+            })
+    }
+
+    /// interrupts属性中的第一个中断号
+    pub fn interrupt(&self) -> Option<u32> {
+        self.prop_u32("interrupts")
+    }
+}
@@ -0,0 +1,201 @@
+//! 设备文件系统
+//!
+//! 根目录下是固定的几个字符设备，不能创建或删除文件。设备忽略读写位置，
+//! 打开后可以像普通文件一样读写，用户程序可以把输出写到/dev/null等设备上
+
+use super::vfs::{DirEntry, FileSystem, FileType, FsError, FsResult, Inode};
+use crate::board;
+use crate::console;
+use crate::sync::SpinMutex;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use riscv::register::time;
+
+/// 一个devfs实例
+pub struct DevFs {
+    root: Arc<DevDir>,
+}
+
+/// devfs的根目录
+struct DevDir {
+    devices: Vec<(&'static str, Arc<CharDevice>)>,
+}
+
+struct CharDevice {
+    ino: u64,
+    kind: DeviceKind,
+}
+
+enum DeviceKind {
+    /// 丢弃写入的数据，读总是返回文件结束
+    Null,
+    /// 读到的都是0，丢弃写入的数据
+    Zero,
+    /// 控制台，读在没有输入时阻塞
+    Console,
+    /// 伪随机数，保存splitmix64的状态，写入的数据被混入状态
+    Random(SpinMutex<u64>),
+}
+
+/// splitmix64，把state前进一步并返回一个伪随机数
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// 把bytes按8字节一组混入state
+fn mix_into(state: &mut u64, bytes: &[u8]) {
+    for chunk in bytes.chunks(8) {
+        let mut word = [0u8; 8];
+        word[..chunk.len()].copy_from_slice(chunk);
+        *state ^= u64::from_le_bytes(word);
+        splitmix64(state);
+    }
+}
+
+/// 随机数种子：time CSR、设备树中的rng-seed以及启动次数
+fn random_seed() -> u64 {
+    let mut seed = time::read() as u64;
+    mix_into(&mut seed, board::machine().rng_seed);
+    mix_into(&mut seed, &board::boot_count().to_le_bytes());
+    seed
+}
+
+impl DevFs {
+    pub fn new() -> Self {
+        let kinds = [
+            ("null", DeviceKind::Null),
+            ("zero", DeviceKind::Zero),
+            ("console", DeviceKind::Console),
+            ("urandom", DeviceKind::Random(SpinMutex::new(random_seed()))),
+        ];
+        // 根目录的inode编号为1
+        let devices = kinds
+            .into_iter()
+            .enumerate()
+            .map(|(index, (name, kind))| {
+                let device = CharDevice {
+                    ino: index as u64 + 2,
+                    kind,
+                };
+                (name, Arc::new(device))
+            })
+            .collect();
+        Self {
+            root: Arc::new(DevDir { devices }),
+        }
+    }
+}
+
+impl FileSystem for DevFs {
+    fn name(&self) -> &'static str {
+        "devfs"
+    }
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        Arc::clone(&self.root) as Arc<dyn Inode>
+    }
+}
+
+impl Inode for DevDir {
+    fn ino(&self) -> u64 {
+        1
+    }
+    fn file_type(&self) -> FileType {
+        FileType::Dir
+    }
+    fn size(&self) -> usize {
+        0
+    }
+    fn nlink(&self) -> u32 {
+        2
+    }
+    fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> FsResult<usize> {
+        Err(FsError::IsDir)
+    }
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> FsResult<usize> {
+        Err(FsError::IsDir)
+    }
+    /// ".."在路径规范化时已经处理，这里只会在devfs自身的根目录中查找
+    fn find(&self, name: &str) -> FsResult<Arc<dyn Inode>> {
+        self.devices
+            .iter()
+            .find(|(device_name, _)| *device_name == name)
+            .map(|(_, device)| Arc::clone(device) as Arc<dyn Inode>)
+            .ok_or(FsError::NotFound)
+    }
+    fn create(&self, _name: &str) -> FsResult<Arc<dyn Inode>> {
+        Err(FsError::NotPermitted)
+    }
+    fn mkdir(&self, _name: &str) -> FsResult<Arc<dyn Inode>> {
+        Err(FsError::NotPermitted)
+    }
+    fn link(&self, _name: &str, _target: &Arc<dyn Inode>) -> FsResult<()> {
+        Err(FsError::NotPermitted)
+    }
+    fn unlink(&self, _name: &str) -> FsResult<Arc<dyn Inode>> {
+        Err(FsError::NotPermitted)
+    }
+    fn dirents(&self) -> FsResult<Vec<DirEntry>> {
+        let dot = |name: &str| DirEntry {
+            name: String::from(name),
+            ino: 1,
+            file_type: FileType::Dir,
+        };
+        let mut dirents = Vec::from([dot("."), dot("..")]);
+        dirents.extend(self.devices.iter().map(|(name, device)| DirEntry {
+            name: String::from(*name),
+            ino: device.ino,
+            file_type: FileType::CharDevice,
+        }));
+        Ok(dirents)
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Inode for CharDevice {
+    fn ino(&self) -> u64 {
+        self.ino
+    }
+    fn file_type(&self) -> FileType {
+        FileType::CharDevice
+    }
+    fn size(&self) -> usize {
+        0
+    }
+    fn read_at(&self, _offset: usize, buf: &mut [u8]) -> FsResult<usize> {
+        match &self.kind {
+            DeviceKind::Null => Ok(0),
+            DeviceKind::Zero => {
+                buf.fill(0);
+                Ok(buf.len())
+            }
+            DeviceKind::Console => Ok(console::read_input(buf)),
+            DeviceKind::Random(state) => {
+                let mut state = state.lock();
+                for chunk in buf.chunks_mut(8) {
+                    let bytes = splitmix64(&mut state).to_le_bytes();
+                    chunk.copy_from_slice(&bytes[..chunk.len()]);
+                }
+                Ok(buf.len())
+            }
+        }
+    }
+    fn write_at(&self, _offset: usize, buf: &[u8]) -> FsResult<usize> {
+        match &self.kind {
+            DeviceKind::Null | DeviceKind::Zero => {}
+            DeviceKind::Console => console::write_bytes(buf),
+            DeviceKind::Random(state) => mix_into(&mut state.lock(), buf),
+        }
+        Ok(buf.len())
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,172 @@
+//! 块设备上的easy-fs，通过VFS接口访问
+
+use super::vfs::{DirEntry, FileSystem, FileType, FsError, FsResult, Inode};
+use crate::drivers::block::block_device;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use easy_fs::{block_cache_sync_all, EasyFileSystem, NAME_LENGTH_LIMIT};
+use lazy_static::*;
+use log::warn;
+
+pub struct EasyFs {
+    root: Arc<easy_fs::Inode>,
+}
+
+/// easy-fs中的一个inode
+pub struct EasyFsInode(Arc<easy_fs::Inode>);
+
+lazy_static! {
+    /// 块设备上的easy-fs，找不到块设备或者设备上不是easy-fs时为None
+    pub static ref DISK_FS: Option<Arc<EasyFs>> = {
+        let device = block_device()?;
+        match EasyFileSystem::open(device) {
+            Some(efs) => Some(Arc::new(EasyFs {
+                root: Arc::new(EasyFileSystem::root_inode(&efs)),
+            })),
+            None => {
+                warn!("block device does not contain an easy-fs image");
+                None
+            }
+        }
+    };
+}
+
+impl FileSystem for EasyFs {
+    fn name(&self) -> &'static str {
+        "easyfs"
+    }
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        Arc::new(EasyFsInode(Arc::clone(&self.root)))
+    }
+    fn sync(&self) {
+        block_cache_sync_all();
+    }
+}
+
+impl EasyFsInode {
+    fn wrap(inode: Arc<easy_fs::Inode>) -> Arc<dyn Inode> {
+        Arc::new(Self(inode))
+    }
+
+    /// easy-fs的create等只返回Option，先检查出具体的失败原因
+    fn check_new_name(&self, name: &str) -> FsResult<()> {
+        if !self.0.is_dir() {
+            return Err(FsError::NotDir);
+        }
+        if name.is_empty() {
+            return Err(FsError::Invalid);
+        }
+        //目录项中的文件名最多NAME_LENGTH_LIMIT字节，不能截断
+        if name.len() > NAME_LENGTH_LIMIT {
+            return Err(FsError::NameTooLong);
+        }
+        if self.0.find(name).is_some() {
+            return Err(FsError::Exists);
+        }
+        Ok(())
+    }
+
+    /// check_new_name通过之后create等仍然失败的原因
+    ///
+    /// 检查之后name可能已经被其他hart创建，否则是inode或数据块不够
+    fn new_name_error(&self, name: &str) -> FsError {
+        if self.0.find(name).is_some() {
+            FsError::Exists
+        } else {
+            FsError::NoSpace
+        }
+    }
+}
+
+impl Inode for EasyFsInode {
+    fn ino(&self) -> u64 {
+        self.0.inode_id() as u64
+    }
+    fn file_type(&self) -> FileType {
+        if self.0.is_dir() {
+            FileType::Dir
+        } else {
+            FileType::File
+        }
+    }
+    fn size(&self) -> usize {
+        self.0.size()
+    }
+    fn nlink(&self) -> u32 {
+        self.0.nlink()
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> FsResult<usize> {
+        Ok(self.0.read_at(offset, buf))
+    }
+    fn write_at(&self, offset: usize, buf: &[u8]) -> FsResult<usize> {
+        self.0.write_at(offset, buf).ok_or(FsError::NoSpace)
+    }
+    fn truncate(&self) -> FsResult<()> {
+        self.0.clear();
+        Ok(())
+    }
+    fn find(&self, name: &str) -> FsResult<Arc<dyn Inode>> {
+        if !self.0.is_dir() {
+            return Err(FsError::NotDir);
+        }
+        self.0.find(name).map(Self::wrap).ok_or(FsError::NotFound)
+    }
+    fn create(&self, name: &str) -> FsResult<Arc<dyn Inode>> {
+        self.check_new_name(name)?;
+        self.0
+            .create(name)
+            .map(Self::wrap)
+            .ok_or_else(|| self.new_name_error(name))
+    }
+    fn mkdir(&self, name: &str) -> FsResult<Arc<dyn Inode>> {
+        self.check_new_name(name)?;
+        self.0
+            .mkdir(name)
+            .map(Self::wrap)
+            .ok_or_else(|| self.new_name_error(name))
+    }
+    fn link(&self, name: &str, target: &Arc<dyn Inode>) -> FsResult<()> {
+        self.check_new_name(name)?;
+        let target = target
+            .as_any()
+            .downcast_ref::<EasyFsInode>()
+            .ok_or(FsError::CrossDevice)?;
+        if target.0.is_dir() {
+            return Err(FsError::NotPermitted);
+        }
+        if self.0.link(name, &target.0) {
+            Ok(())
+        } else {
+            Err(self.new_name_error(name))
+        }
+    }
+    fn unlink(&self, name: &str) -> FsResult<Arc<dyn Inode>> {
+        let inode = self.find(name)?;
+        if inode.is_dir() {
+            return Err(FsError::IsDir);
+        }
+        self.0.unlink(name).map(Self::wrap).ok_or(FsError::NotFound)
+    }
+    fn release(&self) {
+        self.0.release();
+    }
+    fn dirents(&self) -> FsResult<Vec<DirEntry>> {
+        if !self.0.is_dir() {
+            return Err(FsError::NotDir);
+        }
+        Ok(self
+            .0
+            .dirents()
+            .into_iter()
+            .map(|dirent| DirEntry {
+                name: dirent.name,
+                ino: dirent.inode_id as u64,
+                file_type: if dirent.is_dir { FileType::Dir } else { FileType::File },
+            })
+            .collect())
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,124 @@
+//! initramfs
+//!
+//! build.rs把用户程序(bin/)和user/data中的数据文件(data/)打包成newc格式的cpio归档，
+//! 由link_app.S嵌入内核镜像。启动时解包到一个tmpfs中，文件直接引用归档中的数据
+
+use super::tmpfs::{TmpFs, TmpFsInode};
+use super::vfs::{FileSystem, FsError, FsResult, Inode};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::slice;
+use core::str;
+
+const MAGIC: &[u8] = b"070701";
+/// magic和13个8位十六进制数字段
+const HEADER_SIZE: usize = 6 + 13 * 8;
+/// 归档最后一项的文件名
+const TRAILER: &str = "TRAILER!!!";
+
+/// st_mode中的文件类型
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+
+/// 归档中的一项
+struct Entry {
+    /// 相对路径，例如"bin/00hello_world"
+    path: &'static str,
+    mode: u32,
+    data: &'static [u8],
+}
+
+/// 归档在内核镜像中所在的区域[start, end)
+pub fn archive_range() -> (usize, usize) {
+    extern "C" {
+        fn initramfs_start();
+        fn initramfs_end();
+    }
+    (initramfs_start as usize, initramfs_end as usize)
+}
+
+fn archive() -> &'static [u8] {
+    let (start, end) = archive_range();
+    unsafe { slice::from_raw_parts(start as *const u8, end - start) }
+}
+
+/// newc格式中文件名和文件内容都按4字节对齐
+fn align4(pos: usize) -> usize {
+    (pos + 3) & !3
+}
+
+/// 解析归档中的所有项，不包括结尾的TRAILER!!!
+fn parse(archive: &'static [u8]) -> FsResult<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    loop {
+        let header = archive.get(pos..pos + HEADER_SIZE).ok_or(FsError::Invalid)?;
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err(FsError::Invalid);
+        }
+        let field = |index: usize| {
+            let start = MAGIC.len() + index * 8;
+            str::from_utf8(&header[start..start + 8])
+                .ok()
+                .and_then(|hex| usize::from_str_radix(hex, 16).ok())
+                .ok_or(FsError::Invalid)
+        };
+        let mode = field(1)? as u32;
+        let file_size = field(6)?;
+        // 包括结尾的'\0'
+        let name_size = field(11)?;
+        let name_start = pos + HEADER_SIZE;
+        let name = archive
+            .get(name_start..name_start + name_size.saturating_sub(1))
+            .and_then(|name| str::from_utf8(name).ok())
+            .ok_or(FsError::Invalid)?;
+        let data_start = align4(name_start + name_size);
+        let data = archive
+            .get(data_start..data_start + file_size)
+            .ok_or(FsError::Invalid)?;
+        pos = align4(data_start + file_size);
+        if name == TRAILER {
+            return Ok(entries);
+        }
+        entries.push(Entry { path: name, mode, data });
+    }
+}
+
+/// 从root开始查找相对路径path
+fn lookup(root: &Arc<dyn Inode>, path: &str) -> FsResult<Arc<dyn Inode>> {
+    path.split('/')
+        .filter(|name| !name.is_empty() && *name != ".")
+        .try_fold(Arc::clone(root), |dir, name| dir.find(name))
+}
+
+/// 把归档解包到一个新的tmpfs，归档中父目录总是在其中的文件之前
+///
+/// 只处理目录和普通文件，其他类型的项被忽略
+pub fn unpack() -> FsResult<Arc<dyn FileSystem>> {
+    let fs = TmpFs::with_options(None)?;
+    let root = fs.root_inode();
+    for entry in parse(archive())? {
+        let path = entry.path.trim_start_matches("./");
+        let (dir, name) = match path.rsplit_once('/') {
+            Some((dir, name)) => (lookup(&root, dir)?, name),
+            None => (Arc::clone(&root), path),
+        };
+        if name.is_empty() || name == "." {
+            continue;
+        }
+        match entry.mode & S_IFMT {
+            S_IFDIR => match dir.mkdir(name) {
+                Ok(_) | Err(FsError::Exists) => {}
+                Err(err) => return Err(err),
+            },
+            S_IFREG => dir
+                .as_any()
+                .downcast_ref::<TmpFsInode>()
+                .ok_or(FsError::Invalid)?
+                .create_image(name, entry.data)?,
+            _ => {}
+        }
+    }
+    Ok(Arc::new(fs))
+}
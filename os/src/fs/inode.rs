@@ -0,0 +1,237 @@
+//! 打开的文件
+//!
+//! 删除文件的最后一个链接时，如果文件仍被打开，inode要等到最后一个OSInode被drop时才释放
+
+use super::mount::{resolve, resolve_parent};
+use super::vfs::{FileType, FsError, FsResult, Inode};
+use super::{File, Stat, StatMode};
+use crate::sync::SpinMutex;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// 每个inode被打开的次数，以(设备号, inode编号)为键
+///
+/// 打开、创建链接、删除链接和卸载都在持有这个锁时进行，保证不会打开一个正在被释放的inode
+pub(super) static OPEN_COUNT: SpinMutex<BTreeMap<(u64, u64), usize>> = SpinMutex::new(BTreeMap::new());
+
+/// 一个打开的文件，保存打开方式和当前的读写位置
+pub struct OSInode {
+    dev: u64,
+    ino: u64,
+    file_type: FileType,
+    /// 打开时的绝对路径，目录作为dirfd时用它解析相对路径
+    path: String,
+    readable: bool,
+    writable: bool,
+    append: bool,
+    inner: SpinMutex<OSInodeInner>,
+}
+
+struct OSInodeInner {
+    /// 文件中的读写位置，目录中为下一个要读取的目录项的下标
+    offset: usize,
+    inode: Arc<dyn Inode>,
+}
+
+impl OSInode {
+    /// 调用者需要持有OPEN_COUNT并已经增加了打开次数
+    fn new(dev: u64, path: &str, flags: OpenFlags, inode: Arc<dyn Inode>) -> Self {
+        let (readable, writable) = flags.read_write();
+        Self {
+            dev,
+            ino: inode.ino(),
+            file_type: inode.file_type(),
+            path: String::from(path),
+            readable,
+            writable,
+            append: flags.contains(OpenFlags::APPEND),
+            inner: SpinMutex::new(OSInodeInner { offset: 0, inode }),
+        }
+    }
+}
+
+bitflags! {
+    /// openat的flags，取值与Linux一致
+    pub struct OpenFlags: u32 {
+        const RDONLY = 0;
+        const WRONLY = 1 << 0;
+        const RDWR = 1 << 1;
+        const CREATE = 1 << 6;
+        const TRUNC = 1 << 9;
+        const APPEND = 1 << 10;
+        const DIRECTORY = 1 << 16;
+    }
+}
+
+impl OpenFlags {
+    /// (可读, 可写)
+    pub fn read_write(&self) -> (bool, bool) {
+        if self.is_empty() {
+            (true, false)
+        } else if self.contains(Self::WRONLY) {
+            (false, true)
+        } else {
+            (true, self.contains(Self::RDWR))
+        }
+    }
+}
+
+/// 打开规范化的绝对路径path，文件不存在且没有CREATE时返回NotFound
+///
+/// 目录只能以只读方式打开，用于getdents64以及作为*at系统调用的dirfd
+pub fn open_file(path: &str, flags: OpenFlags) -> FsResult<Arc<OSInode>> {
+    let mut open_count = OPEN_COUNT.lock();
+    let (_, writable) = flags.read_write();
+    let (dev, inode) = match resolve(path) {
+        Ok((dev, inode)) => {
+            if inode.is_dir() && writable {
+                return Err(FsError::IsDir);
+            }
+            // 与Linux一致，设备忽略TRUNC
+            if flags.contains(OpenFlags::TRUNC) && writable && inode.file_type() == FileType::File {
+                inode.truncate()?;
+            }
+            (dev, inode)
+        }
+        Err(FsError::NotFound) if flags.contains(OpenFlags::CREATE) => {
+            let (dev, dir, name) = resolve_parent(path)?;
+            (dev, dir.create(name)?)
+        }
+        Err(err) => return Err(err),
+    };
+    if flags.contains(OpenFlags::DIRECTORY) && !inode.is_dir() {
+        return Err(FsError::NotDir);
+    }
+    *open_count.entry((dev, inode.ino())).or_insert(0) += 1;
+    Ok(Arc::new(OSInode::new(dev, path, flags, inode)))
+}
+
+/// 创建目录path
+pub fn mkdir(path: &str) -> FsResult<()> {
+    let _open_count = OPEN_COUNT.lock();
+    let (_, dir, name) = resolve_parent(path)?;
+    dir.mkdir(name).map(|_| ())
+}
+
+/// 为文件old_path创建新的链接new_path，两者必须在同一个文件系统中
+pub fn link_file(old_path: &str, new_path: &str) -> FsResult<()> {
+    let _open_count = OPEN_COUNT.lock();
+    let (old_dev, inode) = resolve(old_path)?;
+    let (new_dev, dir, name) = resolve_parent(new_path)?;
+    if old_dev != new_dev {
+        return Err(FsError::CrossDevice);
+    }
+    dir.link(name, &inode)
+}
+
+/// 删除链接path，删除最后一个链接且文件没有被打开时释放inode
+pub fn unlink_file(path: &str) -> FsResult<()> {
+    let open_count = OPEN_COUNT.lock();
+    let (dev, dir, name) = resolve_parent(path)?;
+    let inode = dir.unlink(name)?;
+    if inode.nlink() == 0 && !open_count.contains_key(&(dev, inode.ino())) {
+        inode.release();
+    }
+    Ok(())
+}
+
+impl Drop for OSInode {
+    /// 最后一次关闭已经没有链接的文件时释放inode
+    fn drop(&mut self) {
+        let mut open_count = OPEN_COUNT.lock();
+        let key = (self.dev, self.ino);
+        let count = open_count.get_mut(&key).unwrap();
+        *count -= 1;
+        if *count == 0 {
+            open_count.remove(&key);
+            let inner = self.inner.lock();
+            if inner.inode.nlink() == 0 {
+                inner.inode.release();
+            }
+        }
+    }
+}
+
+/// getdents64返回的linux_dirent64中name之前的部分：d_ino、d_off、d_reclen、d_type
+const DIRENT64_HEADER_SIZE: usize = 8 + 8 + 2 + 1;
+
+/// linux_dirent64的d_type
+fn dirent_type(file_type: FileType) -> u8 {
+    match file_type {
+        FileType::CharDevice => 2,
+        FileType::Dir => 4,
+        FileType::File => 8,
+    }
+}
+
+impl File for OSInode {
+    /// 目录的内容只能通过getdents64读取
+    fn readable(&self) -> bool {
+        self.readable && self.file_type != FileType::Dir
+    }
+    fn writable(&self) -> bool {
+        self.writable
+    }
+    fn read(&self, buf: &mut [u8]) -> FsResult<usize> {
+        let mut inner = self.inner.lock();
+        let read_size = inner.inode.read_at(inner.offset, buf)?;
+        inner.offset += read_size;
+        Ok(read_size)
+    }
+    fn write(&self, buf: &[u8]) -> FsResult<usize> {
+        let mut inner = self.inner.lock();
+        if self.append {
+            inner.offset = inner.inode.size();
+        }
+        let write_size = inner.inode.write_at(inner.offset, buf)?;
+        inner.offset += write_size;
+        Ok(write_size)
+    }
+    fn stat(&self) -> Stat {
+        let inner = self.inner.lock();
+        let mode = match self.file_type {
+            FileType::File => StatMode::FILE,
+            FileType::Dir => StatMode::DIR,
+            FileType::CharDevice => StatMode::CHR,
+        };
+        Stat::new(
+            self.dev,
+            self.ino,
+            mode,
+            inner.inode.nlink(),
+            inner.inode.size() as u64,
+        )
+    }
+    fn dir_path(&self) -> Option<&str> {
+        (self.file_type == FileType::Dir).then_some(self.path.as_str())
+    }
+    fn getdents(&self, buf: &mut [u8]) -> FsResult<usize> {
+        let mut inner = self.inner.lock();
+        let dirents = inner.inode.dirents()?;
+        let mut written = 0;
+        for (index, dirent) in dirents.iter().enumerate().skip(inner.offset) {
+            // 记录长度包括结尾的'\0'，并按8字节对齐
+            let reclen = (DIRENT64_HEADER_SIZE + dirent.name.len() + 1 + 7) & !7;
+            if written + reclen > buf.len() {
+                break;
+            }
+            let mut record: Vec<u8> = Vec::with_capacity(reclen);
+            record.extend_from_slice(&dirent.ino.to_ne_bytes());
+            record.extend_from_slice(&(index as u64 + 1).to_ne_bytes());
+            record.extend_from_slice(&(reclen as u16).to_ne_bytes());
+            record.push(dirent_type(dirent.file_type));
+            record.extend_from_slice(dirent.name.as_bytes());
+            record.resize(reclen, 0);
+            buf[written..written + reclen].copy_from_slice(&record);
+            written += reclen;
+            inner.offset = index + 1;
+        }
+        // 缓冲区连一个目录项都放不下
+        if written == 0 && inner.offset < dirents.len() {
+            return Err(FsError::Invalid);
+        }
+        Ok(written)
+    }
+}
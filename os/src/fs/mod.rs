@@ -0,0 +1,173 @@
+//! 文件系统
+//!
+//! 具体的文件系统通过`vfs`中的trait接入，挂载在`mount`管理的目录树上。
+//! 块设备上的easy-fs挂载为根目录，它的块缓存是写回的，关机前和sync系统调用时把脏块写回设备；
+//! 没有块设备时用tmpfs作为根目录。/tmp总是挂载一个tmpfs，测试写的临时文件不需要磁盘；
+//! /dev下是null、zero、console和urandom等字符设备；内核镜像中的initramfs解包后挂载在/initramfs，
+//! 其中的bin目录是要运行的app，data目录是app可以读取的数据文件。
+//! app通过文件描述符访问的对象都实现`File`
+
+mod devfs;
+mod easyfs;
+mod initramfs;
+mod inode;
+mod mount;
+mod path;
+mod stdio;
+mod tmpfs;
+mod vfs;
+
+pub use initramfs::archive_range as initramfs_range;
+pub use inode::{link_file, mkdir, open_file, unlink_file, OpenFlags};
+pub use mount::{mount_type, resolve, umount};
+pub use path::normalize;
+pub use stdio::{Stdin, Stdout};
+pub use vfs::{FsError, FsResult, Inode};
+
+use crate::drivers::block::block_device;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use easy_fs::block_cache_stats;
+use easyfs::DISK_FS;
+use tmpfs::TmpFs;
+use log::{error, info};
+
+/// 可以通过文件描述符读写的对象
+pub trait File: Send + Sync {
+    fn readable(&self) -> bool;
+    fn writable(&self) -> bool;
+    /// 读到buf中，返回读到的字节数，0表示文件结束
+    fn read(&self, buf: &mut [u8]) -> FsResult<usize>;
+    /// 写入buf，返回写入的字节数
+    fn write(&self, buf: &[u8]) -> FsResult<usize>;
+    fn stat(&self) -> Stat;
+    /// 打开的目录的绝对路径，用作*at系统调用的dirfd
+    fn dir_path(&self) -> Option<&str> {
+        None
+    }
+    /// 把目录项以linux_dirent64的格式写入buf，返回写入的字节数，0表示已经读完
+    fn getdents(&self, _buf: &mut [u8]) -> FsResult<usize> {
+        Err(FsError::NotDir)
+    }
+}
+
+/// fstat返回的文件信息，与用户库中的定义一致
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct Stat {
+    /// 文件所在的设备
+    pub dev: u64,
+    /// inode编号
+    pub ino: u64,
+    /// 文件类型
+    pub mode: StatMode,
+    /// 硬链接数
+    pub nlink: u32,
+    /// 文件大小
+    pub size: u64,
+    pad: [u64; 6],
+}
+
+bitflags! {
+    /// 文件类型，取值与Linux的st_mode一致
+    #[derive(Default)]
+    pub struct StatMode: u32 {
+        const NULL = 0;
+        const DIR = 0o040000;
+        const CHR = 0o020000;
+        const FILE = 0o100000;
+    }
+}
+
+impl Stat {
+    pub fn new(dev: u64, ino: u64, mode: StatMode, nlink: u32, size: u64) -> Self {
+        Self {
+            dev,
+            ino,
+            mode,
+            nlink,
+            size,
+            pad: [0; 6],
+        }
+    }
+}
+
+/// 读出整个文件
+pub fn read_all(inode: &Arc<dyn Inode>) -> Vec<u8> {
+    let mut buf = vec![0u8; inode.size()];
+    let len = inode.read_at(0, &mut buf).unwrap_or(0);
+    buf.truncate(len);
+    buf
+}
+
+/// 目录path中的普通文件，按文件名排序，path不存在时为空
+pub fn dir_files(path: &str) -> Vec<(String, Arc<dyn Inode>)> {
+    let dir = match resolve(path) {
+        Ok((_, dir)) => dir,
+        Err(_) => return Vec::new(),
+    };
+    let mut files: Vec<(String, Arc<dyn Inode>)> = dir
+        .dirents()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|dirent| dirent.file_type == vfs::FileType::File)
+        .filter_map(|dirent| dir.find(&dirent.name).ok().map(|inode| (dirent.name, inode)))
+        .collect();
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    files
+}
+
+/// 启动时挂载到这些目录的文件系统，挂载点不存在时先创建
+const BOOT_MOUNTS: &[(&str, &str)] = &[("tmpfs", "/tmp"), ("devfs", "/dev")];
+
+/// initramfs的挂载点
+pub const INITRAMFS_PATH: &str = "/initramfs";
+
+/// 挂载根目录、BOOT_MOUNTS中的文件系统以及initramfs
+pub fn init() {
+    match DISK_FS.as_ref() {
+        Some(disk) => {
+            mount::mount(Arc::clone(disk) as Arc<dyn vfs::FileSystem>, "/").unwrap();
+            let names: Vec<String> = dir_files("/").into_iter().map(|(name, _)| name).collect();
+            info!("/: {:?}", names);
+        }
+        None => {
+            let root = TmpFs::with_options(None).unwrap();
+            mount::mount(Arc::new(root), "/").unwrap();
+        }
+    }
+    for &(fstype, path) in BOOT_MOUNTS {
+        mount_at_boot(fstype, path, mount::create_fs(fstype, None));
+    }
+    mount_at_boot("initramfs", INITRAMFS_PATH, initramfs::unpack());
+}
+
+/// 挂载启动时创建的文件系统fs，失败时只打印错误
+fn mount_at_boot(name: &str, path: &str, fs: FsResult<Arc<dyn vfs::FileSystem>>) {
+    let result = fs.and_then(|fs| match mkdir(path) {
+        Ok(()) | Err(FsError::Exists) => mount::mount(fs, path),
+        Err(err) => Err(err),
+    });
+    if let Err(err) = result {
+        error!("failed to mount {} on {}: {:?}", name, path, err);
+    }
+}
+
+/// 把所有文件系统的修改写回设备
+pub fn sync() {
+    mount::sync_all();
+}
+
+/// 打印块缓存的命中统计
+pub fn print_cache_stats() {
+    if block_device().is_none() {
+        return;
+    }
+    let stats = block_cache_stats();
+    info!(
+        "block cache: {} hits, {} misses, {} evictions, {} write-backs",
+        stats.hits, stats.misses, stats.evictions, stats.write_backs
+    );
+}
@@ -0,0 +1,147 @@
+//! 挂载表与路径查找
+//!
+//! 路径先规范化为绝对路径，再按最长前缀选出挂载点，从该文件系统的根目录逐级查找剩下的部分。
+//! ".."在规范化时消去(见`path::normalize`)，所以可以从挂载的文件系统中退回到父文件系统
+
+use super::devfs::DevFs;
+use super::easyfs::DISK_FS;
+use super::inode::OPEN_COUNT;
+use super::tmpfs::TmpFs;
+use super::vfs::{FileSystem, FsError, FsResult, Inode};
+use crate::sync::SpinMutex;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use log::info;
+
+struct Mount {
+    /// 挂载点的绝对路径
+    path: String,
+    /// 文件系统的设备号，同一个文件系统挂载在多处时相同
+    dev: u64,
+    fs: Arc<dyn FileSystem>,
+}
+
+static MOUNTS: SpinMutex<Vec<Mount>> = SpinMutex::new(Vec::new());
+
+fn same_fs(a: &Arc<dyn FileSystem>, b: &Arc<dyn FileSystem>) -> bool {
+    Arc::as_ptr(a) as *const () == Arc::as_ptr(b) as *const ()
+}
+
+/// mount_path是否是path自身或者它的上级目录
+fn covers(mount_path: &str, path: &str) -> bool {
+    mount_path == "/"
+        || path == mount_path
+        || (path.starts_with(mount_path) && path.as_bytes()[mount_path.len()] == b'/')
+}
+
+/// 包含path的最深的挂载点，返回(挂载点路径长度, 设备号, 文件系统)
+fn mount_of(path: &str) -> Option<(usize, u64, Arc<dyn FileSystem>)> {
+    MOUNTS
+        .lock()
+        .iter()
+        .filter(|mount| covers(&mount.path, path))
+        .max_by_key(|mount| mount.path.len())
+        .map(|mount| (mount.path.len(), mount.dev, Arc::clone(&mount.fs)))
+}
+
+/// 查找规范化的绝对路径path，返回它所在文件系统的设备号和inode
+pub fn resolve(path: &str) -> FsResult<(u64, Arc<dyn Inode>)> {
+    let (prefix_len, dev, fs) = mount_of(path).ok_or(FsError::NotFound)?;
+    let mut inode = fs.root_inode();
+    for name in path[prefix_len..].split('/').filter(|name| !name.is_empty()) {
+        inode = inode.find(name)?;
+    }
+    Ok((dev, inode))
+}
+
+/// 查找path所在的目录，返回设备号、目录和最后一级的名字
+pub fn resolve_parent(path: &str) -> FsResult<(u64, Arc<dyn Inode>, &str)> {
+    let pos = path.rfind('/').ok_or(FsError::Invalid)?;
+    let name = &path[pos + 1..];
+    // 根目录没有父目录
+    if name.is_empty() {
+        return Err(FsError::Invalid);
+    }
+    let (dev, dir) = resolve(if pos == 0 { "/" } else { &path[..pos] })?;
+    if !dir.is_dir() {
+        return Err(FsError::NotDir);
+    }
+    Ok((dev, dir, name))
+}
+
+/// 按类型创建要挂载的文件系统，data是mount传入的选项
+pub(super) fn create_fs(fstype: &str, data: Option<&str>) -> FsResult<Arc<dyn FileSystem>> {
+    match fstype {
+        // 只有一个块设备，多次挂载的是同一个easy-fs实例
+        "easyfs" => match DISK_FS.as_ref() {
+            Some(fs) => Ok(Arc::clone(fs) as Arc<dyn FileSystem>),
+            None => Err(FsError::NoDevice),
+        },
+        "tmpfs" => Ok(Arc::new(TmpFs::with_options(data)?)),
+        "devfs" => Ok(Arc::new(DevFs::new())),
+        _ => Err(FsError::NoDevice),
+    }
+}
+
+/// 把fs挂载到规范化的绝对路径path，除了第一次挂载根目录，path必须是已经存在的目录
+pub fn mount(fs: Arc<dyn FileSystem>, path: &str) -> FsResult<()> {
+    let is_first_root = path == "/" && MOUNTS.lock().is_empty();
+    if !is_first_root && !resolve(path)?.1.is_dir() {
+        return Err(FsError::NotDir);
+    }
+    let mut mounts = MOUNTS.lock();
+    if mounts.iter().any(|mount| mount.path == path) {
+        return Err(FsError::Busy);
+    }
+    let dev = match mounts.iter().find(|mount| same_fs(&mount.fs, &fs)) {
+        Some(mount) => mount.dev,
+        None => mounts.iter().map(|mount| mount.dev).max().unwrap_or(0) + 1,
+    };
+    info!("mounted {} on {} (dev {})", fs.name(), path, dev);
+    mounts.push(Mount {
+        path: String::from(path),
+        dev,
+        fs,
+    });
+    Ok(())
+}
+
+/// 挂载fstype类型的文件系统
+pub fn mount_type(fstype: &str, path: &str, data: Option<&str>) -> FsResult<()> {
+    mount(create_fs(fstype, data)?, path)
+}
+
+/// 卸载path上的文件系统
+///
+/// 根目录、其下还有挂载点、或者文件系统只挂载在这里且仍有打开的文件时返回Busy
+pub fn umount(path: &str) -> FsResult<()> {
+    let open_count = OPEN_COUNT.lock();
+    let mut mounts = MOUNTS.lock();
+    let index = mounts
+        .iter()
+        .position(|mount| mount.path == path)
+        .ok_or(FsError::Invalid)?;
+    if path == "/" || mounts.iter().any(|mount| mount.path != path && covers(path, &mount.path)) {
+        return Err(FsError::Busy);
+    }
+    let dev = mounts[index].dev;
+    let mounted_elsewhere = mounts.iter().filter(|mount| mount.dev == dev).count() > 1;
+    if !mounted_elsewhere && open_count.keys().any(|&(open_dev, _)| open_dev == dev) {
+        return Err(FsError::Busy);
+    }
+    let mount = mounts.remove(index);
+    drop(mounts);
+    drop(open_count);
+    mount.fs.sync();
+    info!("unmounted {} from {}", mount.fs.name(), path);
+    Ok(())
+}
+
+/// 把所有挂载的文件系统的修改写回设备
+pub fn sync_all() {
+    let filesystems: Vec<Arc<dyn FileSystem>> = MOUNTS.lock().iter().map(|mount| Arc::clone(&mount.fs)).collect();
+    for fs in filesystems {
+        fs.sync();
+    }
+}
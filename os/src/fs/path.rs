@@ -0,0 +1,55 @@
+//! 路径规范化
+//!
+//! 系统调用中的相对路径先接在当前目录或者dirfd的路径后面，规范化为绝对路径后再查找
+
+use super::mount::resolve;
+use super::vfs::{FsError, FsResult};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// 由各级名字拼出绝对路径
+fn join(names: &[&str]) -> String {
+    let mut path = String::new();
+    for name in names {
+        path.push('/');
+        path.push_str(name);
+    }
+    if path.is_empty() {
+        path.push('/');
+    }
+    path
+}
+
+/// 把path接在绝对路径cwd后面，消去"."、".."和多余的'/'，cwd必须是已经存在的目录
+///
+/// 没有符号链接，".."就是去掉上一级名字，挂载点根目录的".."回到挂载点所在的目录。
+/// 但"."和".."之前的部分必须是存在的目录，与逐级查找目录项的结果一致：
+/// `file/..`返回NotDir，`missing/..`返回NotFound，而不是按字面消去
+pub fn normalize(cwd: &str, path: &str) -> FsResult<String> {
+    let mut names: Vec<&str> = Vec::new();
+    if !path.starts_with('/') {
+        names.extend(cwd.split('/').filter(|name| !name.is_empty()));
+    }
+    //names的前verified级已经确认是目录
+    let mut verified = names.len();
+    for name in path.split('/') {
+        match name {
+            "" => {}
+            "." | ".." => {
+                if names.len() > verified {
+                    let (_, dir) = resolve(&join(&names))?;
+                    if !dir.is_dir() {
+                        return Err(FsError::NotDir);
+                    }
+                    verified = names.len();
+                }
+                if name == ".." {
+                    names.pop();
+                    verified = verified.min(names.len());
+                }
+            }
+            name => names.push(name),
+        }
+    }
+    Ok(join(&names))
+}
@@ -0,0 +1,48 @@
+//! 标准输入输出，每个app的0、1、2号文件描述符
+
+use super::{File, FsResult, Stat, StatMode};
+use crate::console;
+
+/// 控制台输入
+pub struct Stdin;
+
+/// 控制台输出，stdout和stderr共用
+pub struct Stdout;
+
+impl File for Stdin {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        false
+    }
+    /// 没有输入时阻塞，直到至少读到一个字节
+    fn read(&self, buf: &mut [u8]) -> FsResult<usize> {
+        Ok(console::read_input(buf))
+    }
+    fn write(&self, _buf: &[u8]) -> FsResult<usize> {
+        panic!("Cannot write to stdin!");
+    }
+    fn stat(&self) -> Stat {
+        Stat::new(0, 0, StatMode::CHR, 1, 0)
+    }
+}
+
+impl File for Stdout {
+    fn readable(&self) -> bool {
+        false
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, _buf: &mut [u8]) -> FsResult<usize> {
+        panic!("Cannot read from stdout!");
+    }
+    fn write(&self, buf: &[u8]) -> FsResult<usize> {
+        console::write_bytes(buf);
+        Ok(buf.len())
+    }
+    fn stat(&self) -> Stat {
+        Stat::new(0, 1, StatMode::CHR, 1, 0)
+    }
+}
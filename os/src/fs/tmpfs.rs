@@ -0,0 +1,481 @@
+//! 内存文件系统
+//!
+//! 文件内容按页保存在内核堆上，每个实例有自己的容量上限，页用完后写入返回NoSpace。
+//! 所有实例的页和目录项还一起受HEAP_BUDGET限制，不会耗尽内核堆。
+//! 每次挂载都创建一个新的空实例，卸载后其中的内容随之释放。
+//! 解包initramfs时文件直接引用内核镜像中的数据，第一次修改时才复制到页中
+
+use super::vfs::{DirEntry, FileSystem, FileType, FsError, FsResult, Inode};
+use crate::mm::KERNEL_HEAP_SIZE;
+use crate::sync::SpinMutex;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// 保存文件内容的页大小
+const PAGE_SIZE: usize = 4096;
+
+/// 所有tmpfs实例的页和目录项一共最多使用的内核堆空间，其余的留给内核的其他部分
+const HEAP_BUDGET: usize = KERNEL_HEAP_SIZE / 4;
+/// 一个目录项和它指向的inode大致占用的堆空间，不包括名字
+const ENTRY_COST: usize = 256;
+
+/// 已经计入HEAP_BUDGET的字节数
+static HEAP_USED: SpinMutex<usize> = SpinMutex::new(0);
+
+/// 从HEAP_BUDGET中申请最多count份、每份size字节，返回实际申请到的份数
+fn charge(count: usize, size: usize) -> usize {
+    let mut used = HEAP_USED.lock();
+    let count = count.min((HEAP_BUDGET - *used) / size);
+    *used += count * size;
+    count
+}
+
+fn uncharge(bytes: usize) {
+    *HEAP_USED.lock() -= bytes;
+}
+
+/// 在堆上分配一个清零的页，堆空间不够时返回NoSpace而不是panic
+fn alloc_page() -> FsResult<Box<[u8]>> {
+    let mut page = Vec::new();
+    page.try_reserve_exact(PAGE_SIZE).map_err(|_| FsError::NoSpace)?;
+    page.resize(PAGE_SIZE, 0);
+    Ok(page.into_boxed_slice())
+}
+
+/// 没有指定size选项时的容量，编译时可以通过TMPFS_SIZE环境变量修改，格式与size选项相同
+const DEFAULT_SIZE: &str = match option_env!("TMPFS_SIZE") {
+    Some(size) => size,
+    None => "128k",
+};
+
+/// 解析容量，可以带k或m后缀
+fn parse_size(size: &str) -> Option<usize> {
+    let (digits, unit) = match size.as_bytes().last()? {
+        b'k' | b'K' => (&size[..size.len() - 1], 1024),
+        b'm' | b'M' => (&size[..size.len() - 1], 1024 * 1024),
+        _ => (size, 1),
+    };
+    digits.parse::<usize>().ok()?.checked_mul(unit)
+}
+
+/// 一个tmpfs实例
+pub struct TmpFs {
+    root: Arc<TmpFsInode>,
+}
+
+/// 同一个实例中所有inode共享的状态
+struct Shared {
+    /// 最多可以使用的页数，只计文件内容
+    max_pages: usize,
+    usage: SpinMutex<Usage>,
+    next_ino: AtomicU64,
+}
+
+/// 一个实例计入HEAP_BUDGET的空间
+#[derive(Default)]
+struct Usage {
+    pages: usize,
+    entry_bytes: usize,
+}
+
+impl Shared {
+    /// 申请最多count页，返回实际申请到的页数
+    fn reserve(&self, count: usize) -> usize {
+        let mut usage = self.usage.lock();
+        let count = charge(count.min(self.max_pages - usage.pages), PAGE_SIZE);
+        usage.pages += count;
+        count
+    }
+
+    fn unreserve(&self, count: usize) {
+        self.usage.lock().pages -= count;
+        uncharge(count * PAGE_SIZE);
+    }
+
+    /// 为名为name的新目录项申请空间
+    fn reserve_entry(&self, name: &str) -> FsResult<()> {
+        let bytes = ENTRY_COST + name.len();
+        let mut usage = self.usage.lock();
+        if charge(1, bytes) == 0 {
+            return Err(FsError::NoSpace);
+        }
+        usage.entry_bytes += bytes;
+        Ok(())
+    }
+
+    fn unreserve_entry(&self, name: &str) {
+        let bytes = ENTRY_COST + name.len();
+        self.usage.lock().entry_bytes -= bytes;
+        uncharge(bytes);
+    }
+}
+
+/// 实例被卸载并且所有inode都被释放之后，归还它还占用的空间
+impl Drop for Shared {
+    fn drop(&mut self) {
+        let usage = self.usage.lock();
+        uncharge(usage.pages * PAGE_SIZE + usage.entry_bytes);
+    }
+}
+
+/// tmpfs中的文件或目录
+pub struct TmpFsInode {
+    ino: u64,
+    shared: Arc<Shared>,
+    /// 自身的弱引用，作为子目录的".."
+    this: Weak<TmpFsInode>,
+    inner: SpinMutex<TmpFsInodeInner>,
+}
+
+struct TmpFsInodeInner {
+    nlink: u32,
+    content: Content,
+}
+
+enum Content {
+    File {
+        size: usize,
+        pages: Vec<Box<[u8]>>,
+    },
+    /// 内容是内核镜像中的只读数据，不占用页
+    Image(&'static [u8]),
+    Dir {
+        /// 根目录的".."指向自身
+        parent: Weak<TmpFsInode>,
+        entries: BTreeMap<String, Arc<TmpFsInode>>,
+    },
+}
+
+impl TmpFs {
+    /// 容量为size字节的空tmpfs
+    pub fn new(size: usize) -> Self {
+        let shared = Arc::new(Shared {
+            max_pages: size.div_ceil(PAGE_SIZE),
+            usage: SpinMutex::new(Usage::default()),
+            next_ino: AtomicU64::new(1),
+        });
+        Self {
+            root: TmpFsInode::new_dir(&shared, None),
+        }
+    }
+
+    /// 按mount的data创建，目前只支持"size=<字节数>[k|m]"
+    pub fn with_options(data: Option<&str>) -> FsResult<Self> {
+        let mut size = parse_size(DEFAULT_SIZE).expect("TMPFS_SIZE should look like 128k");
+        for option in data.unwrap_or("").split(',').filter(|option| !option.is_empty()) {
+            size = match option.split_once('=') {
+                Some(("size", value)) => parse_size(value).ok_or(FsError::Invalid)?,
+                _ => return Err(FsError::Invalid),
+            };
+        }
+        Ok(Self::new(size))
+    }
+}
+
+impl FileSystem for TmpFs {
+    fn name(&self) -> &'static str {
+        "tmpfs"
+    }
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        Arc::clone(&self.root) as Arc<dyn Inode>
+    }
+}
+
+impl TmpFsInode {
+    fn new(shared: &Arc<Shared>, this: &Weak<Self>, nlink: u32, content: Content) -> Self {
+        Self {
+            ino: shared.next_ino.fetch_add(1, Ordering::Relaxed),
+            shared: Arc::clone(shared),
+            this: Weak::clone(this),
+            inner: SpinMutex::new(TmpFsInodeInner { nlink, content }),
+        }
+    }
+
+    fn new_file(shared: &Arc<Shared>) -> Arc<Self> {
+        Arc::new_cyclic(|this| {
+            let content = Content::File {
+                size: 0,
+                pages: Vec::new(),
+            };
+            Self::new(shared, this, 1, content)
+        })
+    }
+
+    /// 新目录的链接数为2：父目录中的目录项和自身的"."
+    fn new_dir(shared: &Arc<Shared>, parent: Option<&Arc<Self>>) -> Arc<Self> {
+        Arc::new_cyclic(|this| {
+            let content = Content::Dir {
+                parent: parent.map_or_else(|| Weak::clone(this), Arc::downgrade),
+                entries: BTreeMap::new(),
+            };
+            Self::new(shared, this, 2, content)
+        })
+    }
+
+    /// 在目录中创建内容为data的文件，data不会被复制
+    pub fn create_image(&self, name: &str, data: &'static [u8]) -> FsResult<()> {
+        self.add_entry(name, || {
+            Arc::new_cyclic(|this| Self::new(&self.shared, this, 1, Content::Image(data)))
+        })?;
+        Ok(())
+    }
+
+    /// 把Image的内容复制到页中，页不够时返回NoSpace
+    fn copy_image(&self, data: &[u8]) -> FsResult<Content> {
+        let count = data.len().div_ceil(PAGE_SIZE);
+        let reserved = self.shared.reserve(count);
+        if reserved < count {
+            self.shared.unreserve(reserved);
+            return Err(FsError::NoSpace);
+        }
+        let pages = data
+            .chunks(PAGE_SIZE)
+            .map(|chunk| {
+                let mut page = alloc_page()?;
+                page[..chunk.len()].copy_from_slice(chunk);
+                Ok(page)
+            })
+            .collect::<FsResult<Vec<_>>>()
+            .map_err(|err| {
+                self.shared.unreserve(count);
+                err
+            })?;
+        Ok(Content::File {
+            size: data.len(),
+            pages,
+        })
+    }
+
+    fn this(&self) -> Arc<Self> {
+        self.this.upgrade().unwrap()
+    }
+
+    /// 在目录中加入name，由make创建新的inode，目录项计入HEAP_BUDGET
+    fn add_entry(&self, name: &str, make: impl FnOnce() -> Arc<Self>) -> FsResult<Arc<Self>> {
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            return Err(FsError::Invalid);
+        }
+        let mut inner = self.inner.lock();
+        let entries = match &mut inner.content {
+            Content::Dir { entries, .. } => entries,
+            Content::File { .. } | Content::Image(_) => return Err(FsError::NotDir),
+        };
+        if entries.contains_key(name) {
+            return Err(FsError::Exists);
+        }
+        self.shared.reserve_entry(name)?;
+        let inode = make();
+        entries.insert(String::from(name), Arc::clone(&inode));
+        Ok(inode)
+    }
+}
+
+impl Inode for TmpFsInode {
+    fn ino(&self) -> u64 {
+        self.ino
+    }
+    fn file_type(&self) -> FileType {
+        match self.inner.lock().content {
+            Content::File { .. } | Content::Image(_) => FileType::File,
+            Content::Dir { .. } => FileType::Dir,
+        }
+    }
+    fn size(&self) -> usize {
+        match self.inner.lock().content {
+            Content::File { size, .. } => size,
+            Content::Image(data) => data.len(),
+            Content::Dir { .. } => 0,
+        }
+    }
+    fn nlink(&self) -> u32 {
+        self.inner.lock().nlink
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> FsResult<usize> {
+        let inner = self.inner.lock();
+        let (size, pages) = match &inner.content {
+            Content::File { size, pages } => (*size, pages),
+            Content::Image(data) => {
+                let data = data.get(offset..).unwrap_or(&[]);
+                let len = data.len().min(buf.len());
+                buf[..len].copy_from_slice(&data[..len]);
+                return Ok(len);
+            }
+            Content::Dir { .. } => return Err(FsError::IsDir),
+        };
+        if offset >= size {
+            return Ok(0);
+        }
+        let end = size.min(offset.saturating_add(buf.len()));
+        let mut pos = offset;
+        while pos < end {
+            let page_offset = pos % PAGE_SIZE;
+            let len = (PAGE_SIZE - page_offset).min(end - pos);
+            buf[pos - offset..pos - offset + len]
+                .copy_from_slice(&pages[pos / PAGE_SIZE][page_offset..page_offset + len]);
+            pos += len;
+        }
+        Ok(end - offset)
+    }
+    /// 页不够时写入能放下的部分，一个字节也写不下时返回NoSpace
+    fn write_at(&self, offset: usize, buf: &[u8]) -> FsResult<usize> {
+        //offset来自用户的lseek，可能接近usize::MAX
+        let write_end = offset.checked_add(buf.len()).ok_or(FsError::Invalid)?;
+        let mut inner = self.inner.lock();
+        if let Content::Image(data) = inner.content {
+            inner.content = self.copy_image(data)?;
+        }
+        let (size, pages) = match &mut inner.content {
+            Content::File { size, pages } => (size, pages),
+            Content::Image(_) => unreachable!(),
+            Content::Dir { .. } => return Err(FsError::IsDir),
+        };
+        let needed_pages = write_end.div_ceil(PAGE_SIZE);
+        if needed_pages > pages.len() {
+            let count = self.shared.reserve(needed_pages - pages.len());
+            //堆中分配失败的页归还给预算，只写入已经分配到的页
+            let mut allocated = 0;
+            if pages.try_reserve(count).is_ok() {
+                while allocated < count {
+                    match alloc_page() {
+                        Ok(page) => pages.push(page),
+                        Err(_) => break,
+                    }
+                    allocated += 1;
+                }
+            }
+            self.shared.unreserve(count - allocated);
+        }
+        let end = write_end.min(pages.len() * PAGE_SIZE);
+        if end <= offset {
+            return if buf.is_empty() { Ok(0) } else { Err(FsError::NoSpace) };
+        }
+        let mut pos = offset;
+        while pos < end {
+            let page_offset = pos % PAGE_SIZE;
+            let len = (PAGE_SIZE - page_offset).min(end - pos);
+            pages[pos / PAGE_SIZE][page_offset..page_offset + len]
+                .copy_from_slice(&buf[pos - offset..pos - offset + len]);
+            pos += len;
+        }
+        *size = (*size).max(end);
+        Ok(end - offset)
+    }
+    fn truncate(&self) -> FsResult<()> {
+        let mut inner = self.inner.lock();
+        match &mut inner.content {
+            Content::File { size, pages } => {
+                self.shared.unreserve(pages.len());
+                pages.clear();
+                *size = 0;
+                Ok(())
+            }
+            Content::Image(_) => {
+                inner.content = Content::File {
+                    size: 0,
+                    pages: Vec::new(),
+                };
+                Ok(())
+            }
+            Content::Dir { .. } => Err(FsError::IsDir),
+        }
+    }
+    fn find(&self, name: &str) -> FsResult<Arc<dyn Inode>> {
+        let inner = self.inner.lock();
+        let (parent, entries) = match &inner.content {
+            Content::Dir { parent, entries } => (parent, entries),
+            Content::File { .. } | Content::Image(_) => return Err(FsError::NotDir),
+        };
+        let inode = match name {
+            "." => self.this(),
+            ".." => parent.upgrade().ok_or(FsError::NotFound)?,
+            name => Arc::clone(entries.get(name).ok_or(FsError::NotFound)?),
+        };
+        Ok(inode)
+    }
+    fn create(&self, name: &str) -> FsResult<Arc<dyn Inode>> {
+        let inode = self.add_entry(name, || Self::new_file(&self.shared))?;
+        Ok(inode)
+    }
+    /// 新目录的".."使父目录的链接数加1
+    fn mkdir(&self, name: &str) -> FsResult<Arc<dyn Inode>> {
+        let this = self.this();
+        let inode = self.add_entry(name, || Self::new_dir(&self.shared, Some(&this)))?;
+        self.inner.lock().nlink += 1;
+        Ok(inode)
+    }
+    fn link(&self, name: &str, target: &Arc<dyn Inode>) -> FsResult<()> {
+        let target = target
+            .as_any()
+            .downcast_ref::<TmpFsInode>()
+            .filter(|target| Arc::ptr_eq(&target.shared, &self.shared))
+            .ok_or(FsError::CrossDevice)?;
+        if target.file_type() == FileType::Dir {
+            return Err(FsError::NotPermitted);
+        }
+        let target = target.this();
+        self.add_entry(name, || Arc::clone(&target))?;
+        target.inner.lock().nlink += 1;
+        Ok(())
+    }
+    fn unlink(&self, name: &str) -> FsResult<Arc<dyn Inode>> {
+        let inode = {
+            let mut inner = self.inner.lock();
+            let entries = match &mut inner.content {
+                Content::Dir { entries, .. } => entries,
+                Content::File { .. } | Content::Image(_) => return Err(FsError::NotDir),
+            };
+            let inode = entries.get(name).ok_or(FsError::NotFound)?;
+            if inode.file_type() == FileType::Dir {
+                return Err(FsError::IsDir);
+            }
+            entries.remove(name).unwrap()
+        };
+        self.shared.unreserve_entry(name);
+        inode.inner.lock().nlink -= 1;
+        Ok(inode)
+    }
+    fn image(&self) -> Option<&'static [u8]> {
+        match self.inner.lock().content {
+            Content::Image(data) => Some(data),
+            _ => None,
+        }
+    }
+    /// 归还文件占用的页，inode本身在最后一个引用消失时释放；目录不能被删除，不会走到这里
+    fn release(&self) {
+        self.truncate().ok();
+    }
+    fn dirents(&self) -> FsResult<Vec<DirEntry>> {
+        let inner = self.inner.lock();
+        let (parent, entries) = match &inner.content {
+            Content::Dir { parent, entries } => (parent, entries),
+            Content::File { .. } | Content::Image(_) => return Err(FsError::NotDir),
+        };
+        let mut dirents = vec![
+            DirEntry {
+                name: String::from("."),
+                ino: self.ino,
+                file_type: FileType::Dir,
+            },
+            DirEntry {
+                name: String::from(".."),
+                ino: parent.upgrade().map_or(self.ino, |parent| parent.ino),
+                file_type: FileType::Dir,
+            },
+        ];
+        dirents.extend(entries.iter().map(|(name, inode)| DirEntry {
+            name: name.clone(),
+            ino: inode.ino,
+            file_type: inode.file_type(),
+        }));
+        Ok(dirents)
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
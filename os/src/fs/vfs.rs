@@ -0,0 +1,125 @@
+//! 虚拟文件系统接口
+//!
+//! 每种文件系统实现`FileSystem`和`Inode`，挂载到目录树上之后，路径查找、打开文件和
+//! 各个系统调用都只通过这两个trait访问它们
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+
+/// 文件系统操作的错误，系统调用返回对应的负errno
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsError {
+    /// 不允许的操作，例如为目录创建硬链接
+    NotPermitted = 1,
+    NotFound = 2,
+    /// fd不存在，或者打开方式不允许读写
+    BadFd = 9,
+    /// 挂载点上还有打开的文件或者其他挂载
+    Busy = 16,
+    Exists = 17,
+    /// 硬链接不能跨越文件系统
+    CrossDevice = 18,
+    /// 未知的文件系统类型或者没有对应的设备
+    NoDevice = 19,
+    NotDir = 20,
+    IsDir = 21,
+    Invalid = 22,
+    /// 磁盘的inode或数据块、tmpfs的页已经用完
+    NoSpace = 28,
+    /// 文件名超过文件系统的长度限制
+    NameTooLong = 36,
+}
+
+impl FsError {
+    /// 系统调用的返回值
+    pub fn to_errno(self) -> isize {
+        -(self as isize)
+    }
+}
+
+pub type FsResult<T> = Result<T, FsError>;
+
+/// inode的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    File,
+    Dir,
+    CharDevice,
+}
+
+/// 目录中的一项
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    pub name: String,
+    pub ino: u64,
+    pub file_type: FileType,
+}
+
+/// 一种具体文件系统中的文件或目录
+///
+/// 目录相关的方法默认返回NotDir，普通文件和设备只需要实现读写
+pub trait Inode: Send + Sync {
+    /// 文件系统内唯一的inode编号
+    fn ino(&self) -> u64;
+    fn file_type(&self) -> FileType;
+    fn size(&self) -> usize;
+    /// 指向这个inode的目录项数
+    fn nlink(&self) -> u32 {
+        1
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> FsResult<usize>;
+    /// 从offset开始写入buf，必要时扩大文件
+    fn write_at(&self, offset: usize, buf: &[u8]) -> FsResult<usize>;
+    /// 把文件截断为空
+    fn truncate(&self) -> FsResult<()> {
+        Err(FsError::Invalid)
+    }
+    /// 在目录中查找name
+    fn find(&self, _name: &str) -> FsResult<Arc<dyn Inode>> {
+        Err(FsError::NotDir)
+    }
+    /// 在目录中创建一个普通文件
+    fn create(&self, _name: &str) -> FsResult<Arc<dyn Inode>> {
+        Err(FsError::NotDir)
+    }
+    fn mkdir(&self, _name: &str) -> FsResult<Arc<dyn Inode>> {
+        Err(FsError::NotDir)
+    }
+    /// 在目录中创建指向target的目录项name，target与目录属于同一个文件系统
+    fn link(&self, _name: &str, _target: &Arc<dyn Inode>) -> FsResult<()> {
+        Err(FsError::NotDir)
+    }
+    /// 删除目录项name，返回它指向的inode，链接数减为0时也不释放，由调用者决定何时release
+    fn unlink(&self, _name: &str) -> FsResult<Arc<dyn Inode>> {
+        Err(FsError::NotDir)
+    }
+    /// 内容在内存中不会改变的只读映像，例如initramfs中还没有被修改过的文件，加载app时不用复制
+    fn image(&self) -> Option<&'static [u8]> {
+        None
+    }
+    /// 释放链接数为0并且不再被打开的inode
+    fn release(&self) {}
+    /// 列出目录中的所有目录项，包括"."和".."
+    fn dirents(&self) -> FsResult<Vec<DirEntry>> {
+        Err(FsError::NotDir)
+    }
+    /// 用于link时取得同一个文件系统中的具体类型
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl dyn Inode {
+    pub fn is_dir(&self) -> bool {
+        self.file_type() == FileType::Dir
+    }
+}
+
+/// 一个可以被挂载的文件系统实例
+pub trait FileSystem: Send + Sync {
+    /// 文件系统类型，即mount的fstype
+    fn name(&self) -> &'static str;
+    fn root_inode(&self) -> Arc<dyn Inode>;
+    /// 把缓存的修改写回设备
+    fn sync(&self) {}
+}
@@ -1,5 +1,5 @@
 use core::panic::PanicInfo;
-use crate::sbi::shutdown;
+use crate::power::panic_shutdown;
 
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
@@ -13,5 +13,5 @@ fn panic(info: &PanicInfo) -> ! {
     } else {
         println!("[kernel] Panicked: {}", info.message().unwrap());
     }
-    shutdown()
+    panic_shutdown()
 }
\ No newline at end of file
@@ -0,0 +1,104 @@
+//! 内核内存布局
+//!
+//! 启动时根据linker.ld导出的符号打印内核各段以及批处理用到的区域，
+//! 并检查每个app的加载区域是否与内核镜像重叠：内核镜像嵌入了所有app的ELF，
+//! app变多后ekernel可能越过APP_BASE_ADDRESS，加载app时会覆盖内核自身
+
+use crate::batch::{self, APP_BASE_ADDRESS, APP_MAX_SIZE};
+use crate::board;
+use log::info;
+
+/// 区域[start, end)
+type Range = (usize, usize);
+
+fn overlaps(a: Range, b: Range) -> bool {
+    a.0 < b.1 && b.0 < a.1
+}
+
+fn print_region(name: &str, (start, end): Range) {
+    info!(
+        "{:<14} [{:#x}, {:#x}) {:>6} KiB",
+        name,
+        start,
+        end,
+        (end - start + 1023) / 1024
+    );
+}
+
+/// linker.ld中定义的内核镜像范围
+fn kernel_image() -> Range {
+    extern "C" {
+        fn skernel();
+        fn ekernel();
+    }
+    (skernel as usize, ekernel as usize)
+}
+
+/// 打印内存布局
+pub fn print_memory_layout() {
+    extern "C" {
+        fn stext();
+        fn etext();
+        fn srodata();
+        fn erodata();
+        fn sdata();
+        fn edata();
+        fn boot_stack();
+        fn boot_stack_top();
+        fn sbss();
+        fn ebss();
+    }
+    info!("memory layout:");
+    print_region("kernel", kernel_image());
+    print_region(".text", (stext as usize, etext as usize));
+    print_region(".rodata", (srodata as usize, erodata as usize));
+    print_region(".data", (sdata as usize, edata as usize));
+    print_region("boot stack", (boot_stack as usize, boot_stack_top as usize));
+    print_region(".bss", (sbss as usize, ebss as usize));
+    print_region("heap", crate::mm::heap_range());
+    print_region("boot record", board::boot_record_range());
+    print_region("initramfs", crate::fs::initramfs_range());
+    print_region("kernel stacks", batch::kernel_stack_range());
+    print_region("user stacks", batch::user_stack_range());
+    print_region("app regions", app_region(0, batch::app_num()));
+}
+
+/// 第first个到第last-1个app的加载区域
+fn app_region(first: usize, last: usize) -> Range {
+    (
+        APP_BASE_ADDRESS + first * APP_MAX_SIZE,
+        APP_BASE_ADDRESS + last * APP_MAX_SIZE,
+    )
+}
+
+/// 检查每个app的加载区域，与内核镜像或启动记录重叠、或者超出物理内存时拒绝启动
+pub fn check_memory_layout() {
+    let kernel = kernel_image();
+    let boot_record = board::boot_record_range();
+    let machine = board::machine();
+    for app_id in 0..batch::app_num() {
+        let region = app_region(app_id, app_id + 1);
+        if overlaps(region, kernel) || overlaps(region, boot_record) {
+            panic!(
+                "app_{} region [{:#x}, {:#x}) overlaps the kernel image [{:#x}, {:#x}), \
+                 move APP_BASE_ADDRESS (and user/build.py) above ekernel, see also os/src/linker.ld",
+                app_id, region.0, region.1, kernel.0, boot_record.1
+            );
+        }
+        let in_memory = machine
+            .memory()
+            .iter()
+            .any(|&(base, size)| region.0 >= base && region.1 <= base + size);
+        if !in_memory {
+            panic!(
+                "app_{} region [{:#x}, {:#x}) is outside physical memory",
+                app_id, region.0, region.1
+            );
+        }
+    }
+}
+
+pub fn init() {
+    print_memory_layout();
+    check_memory_layout();
+}
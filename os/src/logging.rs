@@ -1,13 +1,77 @@
 //! Global logger
 //!
+//! 编译时通过LOG环境变量设置日志级别，可以只写一个全局级别(LOG=INFO)，
+//! 也可以按模块设置(LOG=batch=trace,trap=info)，两者可以混用(LOG=warn,batch=trace)。
+//! 模块名为去掉`os::`前缀后的模块路径，按最长前缀匹配；没有设置LOG时默认为INFO
+//!
+//! 输出到控制台的同时，每条日志(不带颜色)还会追加到固定大小的环形缓冲区，用户程序可以通过syslog系统调用读取
+use crate::board::clock_freq;
+use crate::smp::hart_id;
+use crate::sync::IrqSafeMutex;
+use core::fmt::{self, Write};
 use log::{self, Level, LevelFilter, Log, Metadata, Record};
+use riscv::register::time;
+
+const LOG_SPEC: Option<&str> = option_env!("LOG");
 
 /// a simple logger
 struct SimpleLogger;
 
+fn parse_level(level: &str) -> Option<LevelFilter> {
+    [
+        ("OFF", LevelFilter::Off),
+        ("ERROR", LevelFilter::Error),
+        ("WARN", LevelFilter::Warn),
+        ("INFO", LevelFilter::Info),
+        ("DEBUG", LevelFilter::Debug),
+        ("TRACE", LevelFilter::Trace),
+    ]
+    .iter()
+    .find(|(name, _)| name.eq_ignore_ascii_case(level.trim()))
+    .map(|&(_, filter)| filter)
+}
+
+/// 解析LOG中的每一项，不带模块名的项返回None作为模块名
+fn directives() -> impl Iterator<Item = (Option<&'static str>, LevelFilter)> {
+    LOG_SPEC
+        .unwrap_or("")
+        .split(',')
+        .filter(|item| !item.trim().is_empty())
+        .filter_map(|item| match item.split_once('=') {
+            Some((module, level)) => parse_level(level).map(|filter| (Some(module.trim()), filter)),
+            None => parse_level(item).map(|filter| (None, filter)),
+        })
+}
+
+/// module是否属于prefix这个模块(本身或者子模块)
+fn module_matches(module: &str, prefix: &str) -> bool {
+    module == prefix
+        || (module.starts_with(prefix) && module[prefix.len()..].starts_with("::"))
+}
+
+/// 模块生效的日志级别
+fn level_of(module_path: &str) -> LevelFilter {
+    let module = module_path.strip_prefix("os::").unwrap_or(module_path);
+    let mut level = LevelFilter::Info;
+    let mut matched_len = None;
+    for (prefix, filter) in directives() {
+        match prefix {
+            Some(prefix) if module_matches(module, prefix) => {
+                if matched_len.map_or(true, |len| prefix.len() >= len) {
+                    level = filter;
+                    matched_len = Some(prefix.len());
+                }
+            }
+            None if matched_len.is_none() => level = filter,
+            _ => {}
+        }
+    }
+    level
+}
+
 impl Log for SimpleLogger {
-    fn enabled(&self, _metadata: &Metadata) -> bool {
-        true
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= level_of(metadata.target())
     }
     fn log(&self, record: &Record) {
         if !self.enabled(record.metadata()) {
@@ -20,10 +84,31 @@ impl Log for SimpleLogger {
             Level::Debug => 32, // Green
             Level::Trace => 90, // BrightBlack
         };
+        let ticks = time::read();
+        let freq = clock_freq();
+        let module = record
+            .module_path()
+            .map(|path| path.strip_prefix("os::").unwrap_or(path))
+            .unwrap_or("");
         println!(
-            "\u{1B}[{}m[{:>5}] {}\u{1B}[0m",
+            "\u{1B}[{}m[{:>5}.{:06}] [{:>5}] [hart {}] {}: {}\u{1B}[0m",
             color,
+            ticks / freq,
+            ticks % freq * 1_000_000 / freq,
+            record.level(),
+            hart_id(),
+            module,
+            record.args(),
+        );
+        //这里的写入不会失败，缓冲区满时覆盖最旧的内容
+        let _ = writeln!(
+            LOG_BUFFER.lock(),
+            "[{:>5}.{:06}] [{:>5}] [hart {}] {}: {}",
+            ticks / freq,
+            ticks % freq * 1_000_000 / freq,
             record.level(),
+            hart_id(),
+            module,
             record.args(),
         );
     }
@@ -34,12 +119,94 @@ impl Log for SimpleLogger {
 pub fn init() {
     static LOGGER: SimpleLogger = SimpleLogger;
     log::set_logger(&LOGGER).unwrap();
-    log::set_max_level(match option_env!("LOG") {
-        Some("ERROR") => LevelFilter::Error,
-        Some("WARN") => LevelFilter::Warn,
-        Some("INFO") => LevelFilter::Info,
-        Some("DEBUG") => LevelFilter::Debug,
-        Some("TRACE") => LevelFilter::Trace,
-        _ => LevelFilter::Off,
-    });
+    //全局最大级别取所有项中最详细的一个，具体到模块的过滤在enabled中完成
+    let max_level = directives()
+        .map(|(_, filter)| filter)
+        .chain(core::iter::once(level_of("")))
+        .max()
+        .unwrap_or(LevelFilter::Info);
+    log::set_max_level(max_level);
+}
+
+/// 日志环形缓冲区的大小
+pub const LOG_BUFFER_SIZE: usize = 16 * 1024;
+
+/// 保存最近日志的环形缓冲区
+pub struct LogBuffer {
+    data: [u8; LOG_BUFFER_SIZE],
+    /// 最旧的字节所在位置
+    head: usize,
+    /// 已保存的字节数
+    len: usize,
+    /// 最新的unread个字节还没有被SYSLOG_ACTION_READ读走，不超过len
+    unread: usize,
+}
+
+static LOG_BUFFER: IrqSafeMutex<LogBuffer> = IrqSafeMutex::new(LogBuffer {
+    data: [0; LOG_BUFFER_SIZE],
+    head: 0,
+    len: 0,
+    unread: 0,
+});
+
+impl Write for LogBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for &byte in s.as_bytes() {
+            let tail = (self.head + self.len) % LOG_BUFFER_SIZE;
+            self.data[tail] = byte;
+            if self.len == LOG_BUFFER_SIZE {
+                //已满，覆盖最旧的字节
+                self.head = (self.head + 1) % LOG_BUFFER_SIZE;
+            } else {
+                self.len += 1;
+            }
+            self.unread = (self.unread + 1).min(self.len);
+        }
+        Ok(())
+    }
+}
+
+/// 把缓冲区中最新的至多buf.len()个字节按时间顺序复制到buf，clear为true时随后清空缓冲区
+///
+/// 返回复制的字节数
+pub fn read_log_buffer(buf: &mut [u8], clear: bool) -> usize {
+    let mut log_buffer = LOG_BUFFER.lock();
+    let count = buf.len().min(log_buffer.len);
+    let start = log_buffer.head + log_buffer.len - count;
+    for (i, byte) in buf[..count].iter_mut().enumerate() {
+        *byte = log_buffer.data[(start + i) % LOG_BUFFER_SIZE];
+    }
+    if clear {
+        log_buffer.head = 0;
+        log_buffer.len = 0;
+        log_buffer.unread = 0;
+    }
+    count
+}
+
+/// 从读指针开始按时间顺序把至多buf.len()个未读字节复制到buf，并向后移动读指针
+///
+/// 返回复制的字节数，没有未读字节时返回0
+pub fn read_log_unread(buf: &mut [u8]) -> usize {
+    let mut log_buffer = LOG_BUFFER.lock();
+    let count = buf.len().min(log_buffer.unread);
+    let start = log_buffer.head + log_buffer.len - log_buffer.unread;
+    for (i, byte) in buf[..count].iter_mut().enumerate() {
+        *byte = log_buffer.data[(start + i) % LOG_BUFFER_SIZE];
+    }
+    log_buffer.unread -= count;
+    count
+}
+
+/// 清空缓冲区
+pub fn clear_log_buffer() {
+    let mut log_buffer = LOG_BUFFER.lock();
+    log_buffer.head = 0;
+    log_buffer.len = 0;
+    log_buffer.unread = 0;
+}
+
+/// 缓冲区中还没有被SYSLOG_ACTION_READ读走的字节数
+pub fn log_unread_len() -> usize {
+    LOG_BUFFER.lock().unread
 }
@@ -2,7 +2,12 @@
 #![no_main]
 #![no_std]
 
+extern crate alloc;
+#[macro_use]
+extern crate bitflags;
+
 use core::arch::global_asm;
+use log::info;
 
 
 #[macro_use]
@@ -10,13 +15,21 @@ mod console;
 mod lang_items;
 mod syscall;
 mod sbi;
+mod fdt;
+mod fs;
+mod layout;
 mod logging;
+mod mm;
 mod sync;
 mod batch;
 mod board;
+mod drivers;
+mod power;
+mod smp;
+mod task;
 mod trap;
 
-global_asm!(include_str!("entry.asm"));
+global_asm!(include_str!("entry.asm"), max_harts = const smp::MAX_HARTS);
 global_asm!(include_str!("link_app.S"));
 
 fn clear_bss(){
@@ -31,11 +44,32 @@ fn clear_bss(){
 
 // 一定要加no_mangle属性告诉编译器不要修改函数名称
 #[no_mangle]
-pub fn rust_main() -> !{
+pub fn rust_main(hart_id: usize, dtb: usize) -> !{
+    if !smp::try_become_boot_hart(hart_id) {
+        smp::wait_for_boot_hart();
+        secondary_main(hart_id);
+    }
     clear_bss();
-    println!("[kernel] Hello, world!");
+    mm::init_heap();
+    logging::init();
+    info!("Hello, world! (boot #{}, hart {})", board::record_boot(), hart_id);
+    sbi::init();
+    board::init(dtb);
+    drivers::init();
+    fs::init();
+    layout::init();
     trap::init();
+    drivers::init_hart();
     batch::init();
+    smp::start_secondary_harts(dtb);
+    batch::run_next_app();
+}
+
+/// 其他hart只需要完成本hart的初始化，然后进入批处理运行app
+fn secondary_main(hart_id: usize) -> ! {
+    info!("hart {} started", hart_id);
+    trap::init();
+    drivers::init_hart();
     batch::run_next_app();
 }
 
@@ -45,5 +79,8 @@ qemu-system-riscv64 \
             -machine virt \
             -nographic \
             -bios ../bootloader/rustsbi-qemu.bin \
-            -device loader,file=target/riscv64gc-unknown-none-elf/debug/os,addr=0x80200000
+            -smp 4 \
+            -device loader,file=target/riscv64gc-unknown-none-elf/debug/os,addr=0x80200000 \
+            -drive file=../user/target/riscv64gc-unknown-none-elf/release/fs.img,if=none,format=raw,id=x0 \
+            -device virtio-blk-device,drive=x0,bus=virtio-mmio-bus.0
 */
\ No newline at end of file
@@ -0,0 +1,27 @@
+//! 内核堆
+//!
+//! 堆空间是.bss中的一个静态数组，必须在clear_bss之后初始化
+
+use buddy_system_allocator::LockedHeap;
+
+/// 内核堆大小，内核镜像必须仍然位于APP_BASE_ADDRESS之下，见layout::check_memory_layout
+pub const KERNEL_HEAP_SIZE: usize = 0x8_0000;
+
+#[global_allocator]
+static HEAP_ALLOCATOR: LockedHeap = LockedHeap::empty();
+
+static mut HEAP_SPACE: [u8; KERNEL_HEAP_SIZE] = [0; KERNEL_HEAP_SIZE];
+
+pub fn init_heap() {
+    unsafe {
+        HEAP_ALLOCATOR
+            .lock()
+            .init(core::ptr::addr_of!(HEAP_SPACE) as usize, KERNEL_HEAP_SIZE);
+    }
+}
+
+/// 内核堆所在的区域[start, end)
+pub fn heap_range() -> (usize, usize) {
+    let start = unsafe { core::ptr::addr_of!(HEAP_SPACE) as usize };
+    (start, start + KERNEL_HEAP_SIZE)
+}
@@ -0,0 +1,7 @@
+//! 内存管理
+//!
+//! 目前没有开启分页，只有供alloc使用的内核堆
+
+mod heap_allocator;
+
+pub use heap_allocator::{heap_range, init_heap, KERNEL_HEAP_SIZE};
@@ -0,0 +1,70 @@
+//! 关机与重启
+//!
+//! 优先使用SBI System Reset扩展，RustSBI不支持时退回到直接写QEMU的sifive_test设备。
+//! 正常关机和重启前先把文件系统的块缓存写回磁盘
+
+use crate::board::{self, QEMUExit};
+use core::arch::asm;
+use crate::sbi::{self, Extension, ResetReason, ResetType};
+use log::error;
+
+/// 复位系统，SRST不可用或调用失败时改用sifive_test设备
+fn system_reset(reset_type: ResetType, reason: ResetReason) -> ! {
+    if sbi::has_extension(Extension::SystemReset) {
+        let ret = sbi::system_reset(reset_type, reason);
+        error!("SBI system reset {:?} failed: {:?}", reset_type, ret.into_result());
+    }
+    let handle = match board::qemu_exit_handle() {
+        Some(handle) => handle,
+        None => {
+            error!("no sifive_test device, halting");
+            loop {
+                unsafe { asm!("wfi") };
+            }
+        }
+    };
+    match (reset_type, reason) {
+        (ResetType::Shutdown, ResetReason::NoReason) => handle.exit_success(),
+        (ResetType::Shutdown, ResetReason::SystemFailure) => handle.exit_failure(),
+        // sifive_test设备不区分冷热重启
+        _ => handle.reset(),
+    }
+}
+
+/// 写回块缓存并打印统计
+fn flush_filesystem() {
+    crate::fs::sync();
+    crate::fs::print_cache_stats();
+}
+
+/// 关机，failure为true时以失败原因退出(QEMU的退出码为1)
+pub fn shutdown(failure: bool) -> ! {
+    flush_filesystem();
+    let reason = if failure {
+        ResetReason::SystemFailure
+    } else {
+        ResetReason::NoReason
+    };
+    system_reset(ResetType::Shutdown, reason)
+}
+
+/// 重启，warm为true时进行热重启
+pub fn reboot(warm: bool, failure: bool) -> ! {
+    flush_filesystem();
+    let reset_type = if warm {
+        ResetType::WarmReboot
+    } else {
+        ResetType::ColdReboot
+    };
+    let reason = if failure {
+        ResetReason::SystemFailure
+    } else {
+        ResetReason::NoReason
+    };
+    system_reset(reset_type, reason)
+}
+
+/// panic时关机，panic的位置可能持有块缓存的锁，不再访问文件系统
+pub fn panic_shutdown() -> ! {
+    system_reset(ResetType::Shutdown, ResetReason::SystemFailure)
+}
@@ -0,0 +1,83 @@
+//! SBI Base Extension (EID 0x10)
+//!
+//! 所有SBI v0.2+实现都必须支持，用于查询规范版本、实现信息以及探测其他扩展
+
+use super::sbi_call_ext;
+
+pub const EID_BASE: usize = 0x10;
+pub const EID_TIME: usize = 0x5449_4D45;
+pub const EID_IPI: usize = 0x73_5049;
+pub const EID_RFENCE: usize = 0x5246_4E43;
+pub const EID_HSM: usize = 0x48_534D;
+pub const EID_SRST: usize = 0x5352_5354;
+pub const EID_PMU: usize = 0x50_4D55;
+pub const EID_DBCN: usize = 0x4442_434E;
+
+const FID_GET_SPEC_VERSION: usize = 0;
+const FID_GET_IMPL_ID: usize = 1;
+const FID_GET_IMPL_VERSION: usize = 2;
+const FID_PROBE_EXTENSION: usize = 3;
+const FID_GET_MVENDORID: usize = 4;
+const FID_GET_MARCHID: usize = 5;
+const FID_GET_MIMPID: usize = 6;
+
+/// SBI规范版本，bit[30:24]为major，bit[23:0]为minor
+#[derive(Debug, Clone, Copy)]
+pub struct SpecVersion {
+    pub major: usize,
+    pub minor: usize,
+}
+
+/// 查询SBI规范版本
+///
+/// SBI v0.1的实现不认识Base扩展，会返回错误，此时视为v0.1
+pub fn get_spec_version() -> SpecVersion {
+    match sbi_call_ext(EID_BASE, FID_GET_SPEC_VERSION, 0, 0, 0).into_result() {
+        Ok(version) => SpecVersion {
+            major: (version >> 24) & 0x7f,
+            minor: version & 0xff_ffff,
+        },
+        Err(_) => SpecVersion { major: 0, minor: 1 },
+    }
+}
+
+pub fn get_impl_id() -> usize {
+    sbi_call_ext(EID_BASE, FID_GET_IMPL_ID, 0, 0, 0).value
+}
+
+pub fn get_impl_version() -> usize {
+    sbi_call_ext(EID_BASE, FID_GET_IMPL_VERSION, 0, 0, 0).value
+}
+
+/// 探测扩展是否可用，返回值非0即为可用
+pub fn probe_extension(eid: usize) -> bool {
+    sbi_call_ext(EID_BASE, FID_PROBE_EXTENSION, eid, 0, 0).value != 0
+}
+
+pub fn get_mvendorid() -> usize {
+    sbi_call_ext(EID_BASE, FID_GET_MVENDORID, 0, 0, 0).value
+}
+
+pub fn get_marchid() -> usize {
+    sbi_call_ext(EID_BASE, FID_GET_MARCHID, 0, 0, 0).value
+}
+
+pub fn get_mimpid() -> usize {
+    sbi_call_ext(EID_BASE, FID_GET_MIMPID, 0, 0, 0).value
+}
+
+/// SBI规范中登记的实现ID
+pub fn impl_name(impl_id: usize) -> &'static str {
+    match impl_id {
+        0 => "Berkeley Boot Loader (BBL)",
+        1 => "OpenSBI",
+        2 => "Xvisor",
+        3 => "KVM",
+        4 => "RustSBI",
+        5 => "Diosix",
+        6 => "Coffer",
+        7 => "Xen Project",
+        8 => "PolarFire Hart Software Services",
+        _ => "unknown",
+    }
+}
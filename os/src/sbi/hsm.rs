@@ -0,0 +1,56 @@
+//! SBI Hart State Management Extension (EID "HSM")
+
+use super::{sbi_call_ext, SbiError, EID_HSM};
+
+const FID_HART_START: usize = 0;
+const FID_HART_STOP: usize = 1;
+const FID_HART_GET_STATUS: usize = 2;
+
+/// hart_get_status返回的hart状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HartState {
+    Started,
+    Stopped,
+    StartPending,
+    StopPending,
+    Suspended,
+    SuspendPending,
+    ResumePending,
+    Unknown(usize),
+}
+
+impl From<usize> for HartState {
+    fn from(state: usize) -> Self {
+        match state {
+            0 => HartState::Started,
+            1 => HartState::Stopped,
+            2 => HartState::StartPending,
+            3 => HartState::StopPending,
+            4 => HartState::Suspended,
+            5 => HartState::SuspendPending,
+            6 => HartState::ResumePending,
+            _ => HartState::Unknown(state),
+        }
+    }
+}
+
+/// 让处于STOPPED状态的hart从start_addr开始以S模式执行，a0为hartid，a1为opaque
+pub fn hart_start(hart_id: usize, start_addr: usize, opaque: usize) -> Result<(), SbiError> {
+    sbi_call_ext(EID_HSM, FID_HART_START, hart_id, start_addr, opaque)
+        .into_result()
+        .map(|_| ())
+}
+
+/// 停止当前hart，成功时不会返回
+pub fn hart_stop() -> Result<(), SbiError> {
+    sbi_call_ext(EID_HSM, FID_HART_STOP, 0, 0, 0)
+        .into_result()
+        .map(|_| ())
+}
+
+/// 查询hart的状态，hartid不存在时返回InvalidParam
+pub fn hart_get_status(hart_id: usize) -> Result<HartState, SbiError> {
+    sbi_call_ext(EID_HSM, FID_HART_GET_STATUS, hart_id, 0, 0)
+        .into_result()
+        .map(HartState::from)
+}
@@ -0,0 +1,21 @@
+//! SBI IPI Extension (EID "sPI")
+
+use super::{has_extension, sbi_call, sbi_call_ext, Extension, SbiError, EID_IPI};
+
+const FID_SEND_IPI: usize = 0;
+/// Legacy Extensions中的sbi_send_ipi
+const SBI_SEND_IPI: usize = 4;
+
+/// 向hart_mask_base + i(hart_mask的第i位为1)的hart发送核间中断，它们的sip.SSIP会被置位
+pub fn send_ipi(hart_mask: usize, hart_mask_base: usize) -> Result<(), SbiError> {
+    if has_extension(Extension::Ipi) {
+        sbi_call_ext(EID_IPI, FID_SEND_IPI, hart_mask, hart_mask_base, 0)
+            .into_result()
+            .map(|_| ())
+    } else {
+        //旧接口传入的是指向hart mask的指针，且不支持base
+        let mask = hart_mask << hart_mask_base;
+        sbi_call(SBI_SEND_IPI, &mask as *const usize as usize, 0, 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+#![allow(unused)]
+
+///risc v SBI用于进入M级别的特权
+///risc v SBI reference: https://github.com/riscv-non-isa/riscv-sbi-doc/blob/master/riscv-sbi.adoc
+
+mod base;
+mod hsm;
+mod ipi;
+mod srst;
+
+pub use base::*;
+pub use hsm::*;
+pub use ipi::*;
+pub use srst::*;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use log::info;
+
+const SBI_SET_TIMER: usize = 0;
+const SBI_CONSOLE_PUTCHAR: usize = 1;
+const SBI_CONSOLE_GETCHAR: usize = 2;
+const SBI_SHUTDOWN: usize = 8;
+
+/// SBI v0.2+调用的返回值，error位于a0，value位于a1
+#[derive(Debug, Clone, Copy)]
+pub struct SbiRet {
+    pub error: isize,
+    pub value: usize,
+}
+
+/// SBI标准错误码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SbiError {
+    Failed,
+    NotSupported,
+    InvalidParam,
+    Denied,
+    InvalidAddress,
+    AlreadyAvailable,
+    AlreadyStarted,
+    AlreadyStopped,
+    Unknown(isize),
+}
+
+impl From<isize> for SbiError {
+    fn from(code: isize) -> Self {
+        match code {
+            -1 => SbiError::Failed,
+            -2 => SbiError::NotSupported,
+            -3 => SbiError::InvalidParam,
+            -4 => SbiError::Denied,
+            -5 => SbiError::InvalidAddress,
+            -6 => SbiError::AlreadyAvailable,
+            -7 => SbiError::AlreadyStarted,
+            -8 => SbiError::AlreadyStopped,
+            _ => SbiError::Unknown(code),
+        }
+    }
+}
+
+impl SbiRet {
+    /// 把SbiRet转换为Result，error为0(SBI_SUCCESS)时返回value
+    pub fn into_result(self) -> Result<usize, SbiError> {
+        match self.error {
+            0 => Ok(self.value),
+            code => Err(SbiError::from(code)),
+        }
+    }
+}
+
+#[inline(always)]
+fn sbi_call(which: usize, arg0: usize, arg1: usize, arg2: usize) -> usize {
+    /*
+    sbi call实际上会返回error和value，分别位于a0和a1，
+    但是本函数使用Legacy Extensions只需要将FID置0,并只返回a0
+    a6放FID,a7放EID
+     */
+    let mut ret;
+    unsafe {
+        core::arch::asm!(
+        "li x16, 0",    // li: load immediate
+        "ecall",
+        inlateout("a0") arg0 => ret,
+        in("a1") arg1,
+        in("a2") arg2,
+        in("a7") which,
+        );
+    }
+    ret
+}
+
+/// SBI v0.2+调用：a7放EID，a6放FID，返回a0(error)和a1(value)
+#[inline(always)]
+fn sbi_call_ext(eid: usize, fid: usize, arg0: usize, arg1: usize, arg2: usize) -> SbiRet {
+    let (error, value);
+    unsafe {
+        core::arch::asm!(
+        "ecall",
+        inlateout("a0") arg0 => error,
+        inlateout("a1") arg1 => value,
+        in("a2") arg2,
+        in("a6") fid,
+        in("a7") eid,
+        );
+    }
+    SbiRet { error, value }
+}
+
+/// 启动时探测得到的扩展集合，每一位对应`Extension`在`EXTENSIONS`中的下标
+///
+/// init之前为0，此时所有封装函数都使用Legacy Extensions
+static AVAILABLE: AtomicUsize = AtomicUsize::new(0);
+
+/// 内核关心的SBI扩展
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extension {
+    Base,
+    Timer,
+    Ipi,
+    Rfence,
+    Hsm,
+    SystemReset,
+    Pmu,
+    DebugConsole,
+    LegacyConsolePutchar,
+    LegacyShutdown,
+}
+
+const EXTENSIONS: [(Extension, usize, &str); 10] = [
+    (Extension::Base, EID_BASE, "Base"),
+    (Extension::Timer, EID_TIME, "Timer"),
+    (Extension::Ipi, EID_IPI, "IPI"),
+    (Extension::Rfence, EID_RFENCE, "RFENCE"),
+    (Extension::Hsm, EID_HSM, "Hart State Management"),
+    (Extension::SystemReset, EID_SRST, "System Reset"),
+    (Extension::Pmu, EID_PMU, "Performance Monitoring Unit"),
+    (Extension::DebugConsole, EID_DBCN, "Debug Console"),
+    (Extension::LegacyConsolePutchar, SBI_CONSOLE_PUTCHAR, "Legacy Console Putchar"),
+    (Extension::LegacyShutdown, SBI_SHUTDOWN, "Legacy System Shutdown"),
+];
+
+/// 启动时是否探测到了该扩展
+pub fn has_extension(ext: Extension) -> bool {
+    AVAILABLE.load(Ordering::Relaxed) & (1 << ext as usize) != 0
+}
+
+/// 探测SBI实现支持的扩展并打印出来
+///
+/// SBI v0.1只有Legacy Extensions，没有Base扩展，此时保持全部使用Legacy调用
+pub fn init() {
+    let spec = get_spec_version();
+    if spec.major == 0 && spec.minor < 2 {
+        info!("SBI spec v{}.{}, using legacy extensions only", spec.major, spec.minor);
+        return;
+    }
+    let impl_id = get_impl_id();
+    info!(
+        "SBI spec v{}.{}, implementation: {} (id {}) version {:#x}",
+        spec.major,
+        spec.minor,
+        impl_name(impl_id),
+        impl_id,
+        get_impl_version()
+    );
+    let mut available = 0;
+    for (ext, eid, name) in EXTENSIONS {
+        let supported = probe_extension(eid);
+        if supported {
+            available |= 1 << ext as usize;
+        }
+        info!(
+            "{:#010x} {:<28} {}",
+            eid,
+            name,
+            if supported { "yes" } else { "no" }
+        );
+    }
+    AVAILABLE.store(available, Ordering::Relaxed);
+}
+
+pub fn set_timer(timer: usize) {
+    if has_extension(Extension::Timer) {
+        sbi_call_ext(EID_TIME, 0, timer, 0, 0);
+    } else {
+        sbi_call(SBI_SET_TIMER, timer, 0, 0);
+    }
+}
+
+pub fn console_putchar(c: usize) {
+    if has_extension(Extension::DebugConsole) {
+        // sbi_debug_console_write_byte
+        sbi_call_ext(EID_DBCN, 2, c & 0xff, 0, 0);
+    } else {
+        sbi_call(SBI_CONSOLE_PUTCHAR, c, 0, 0);
+    }
+}
+
+pub fn console_getchar() -> usize {
+    sbi_call(SBI_CONSOLE_GETCHAR, 0, 0, 0)
+}
@@ -0,0 +1,25 @@
+//! SBI System Reset Extension (EID "SRST")
+
+use super::{sbi_call_ext, SbiRet, EID_SRST};
+
+const FID_SYSTEM_RESET: usize = 0;
+
+/// reset_type参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetType {
+    Shutdown = 0,
+    ColdReboot = 1,
+    WarmReboot = 2,
+}
+
+/// reset_reason参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetReason {
+    NoReason = 0,
+    SystemFailure = 1,
+}
+
+/// 请求复位整个系统，成功时不会返回
+pub fn system_reset(reset_type: ResetType, reason: ResetReason) -> SbiRet {
+    sbi_call_ext(EID_SRST, FID_SYSTEM_RESET, reset_type as usize, reason as usize, 0)
+}
@@ -0,0 +1,112 @@
+//! 多核启动
+//!
+//! 抢到`BOOT_HART`的hart负责清空.bss等全局初始化，完成后通过SBI HSM扩展启动其他hart，
+//! 所有hart最终都进入`batch::run_next_app`并行运行app
+
+use crate::board;
+use crate::sbi::{self, Extension, HartState, SbiError};
+use core::arch::asm;
+use core::hint::spin_loop;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use log::{error, info, warn};
+
+/// 支持的最大hart数量，与entry.asm中的启动栈数量一致
+pub const MAX_HARTS: usize = 8;
+
+/// 负责全局初始化的hart，初值非0使其位于.data而不是.bss，不会被clear_bss清掉
+static BOOT_HART: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+const BOOTING: usize = 1;
+const BOOTED: usize = 2;
+/// 全局初始化是否完成，同样不能放在.bss中
+static BOOT_STAGE: AtomicUsize = AtomicUsize::new(BOOTING);
+
+/// 进入调度的hart数量，由启动hart在启动其他hart时统计
+static ONLINE_HARTS: AtomicUsize = AtomicUsize::new(1);
+
+/// 当前hart的id，内核态下tp始终保存hartid
+#[inline(always)]
+pub fn hart_id() -> usize {
+    let id;
+    unsafe {
+        asm!("mv {}, tp", out(reg) id);
+    }
+    id
+}
+
+/// 竞争成为启动hart
+///
+/// SBI通常只让一个hart进入内核，但也有实现会让所有hart同时从_start开始执行
+pub fn try_become_boot_hart(hart_id: usize) -> bool {
+    BOOT_HART
+        .compare_exchange(usize::MAX, hart_id, Ordering::AcqRel, Ordering::Acquire)
+        .is_ok()
+}
+
+/// 其他hart等待启动hart完成全局初始化
+pub fn wait_for_boot_hart() {
+    while BOOT_STAGE.load(Ordering::Acquire) != BOOTED {
+        spin_loop();
+    }
+}
+
+/// 进入调度的hart数量
+pub fn online_harts() -> usize {
+    ONLINE_HARTS.load(Ordering::Acquire)
+}
+
+/// 全局初始化完成后由启动hart调用，通过HSM hart_start启动其他hart
+///
+/// hart_start的opaque参数会出现在新hart的a1中，这里传入DTB地址，与启动hart的入口参数一致
+pub fn start_secondary_harts(dtb: usize) {
+    extern "C" {
+        fn _start();
+    }
+    let boot_hart = hart_id();
+    let mut online = 1;
+    if sbi::has_extension(Extension::Hsm) {
+        for id in (0..MAX_HARTS.min(board::machine().harts)).filter(|&id| id != boot_hart) {
+            let started = match sbi::hart_get_status(id) {
+                // hartid不存在
+                Err(SbiError::InvalidParam) => break,
+                Err(err) => {
+                    warn!("hart {}: get status failed: {:?}", id, err);
+                    false
+                }
+                Ok(HartState::Stopped) => match sbi::hart_start(id, _start as usize, dtb) {
+                    Ok(()) => true,
+                    Err(err) => {
+                        warn!("hart {}: hart_start failed: {:?}", id, err);
+                        false
+                    }
+                },
+                // SBI已经让这个hart进入了内核，它正在wait_for_boot_hart中等待
+                Ok(HartState::Started) | Ok(HartState::StartPending) => true,
+                Ok(state) => {
+                    warn!("hart {}: unexpected state {:?}", id, state);
+                    false
+                }
+            };
+            if started {
+                online += 1;
+            }
+        }
+    }
+    info!("{} hart(s) online", online);
+    ONLINE_HARTS.store(online, Ordering::Release);
+    BOOT_STAGE.store(BOOTED, Ordering::Release);
+}
+
+/// 向mask中的hart发送核间中断，用于唤醒在wfi中等待的hart
+pub fn send_ipi(mask: usize) {
+    if let Err(err) = sbi::send_ipi(mask, 0) {
+        error!("send ipi to {:#x} failed: {:?}", mask, err);
+    }
+}
+
+/// 清除当前hart待处理的核间中断(sip.SSIP)
+pub fn clear_ipi() {
+    unsafe {
+        asm!("csrc sip, {}", in(reg) 1 << 1);
+    }
+}
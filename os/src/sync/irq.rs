@@ -0,0 +1,102 @@
+use super::{SpinMutex, SpinMutexGuard};
+use crate::smp::{hart_id, MAX_HARTS};
+use core::cell::UnsafeCell;
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+use riscv::register::sstatus;
+
+/// 每个hart关中断的嵌套状态，只会被本hart在关中断时访问
+#[derive(Clone, Copy)]
+struct IntrState {
+    /// push_off的嵌套层数
+    depth: usize,
+    /// 最外层push_off之前sstatus.SIE的值
+    sie_before: bool,
+}
+
+struct PerHartIntrState([UnsafeCell<IntrState>; MAX_HARTS]);
+
+unsafe impl Sync for PerHartIntrState {}
+
+const INTR_ENABLED: UnsafeCell<IntrState> = UnsafeCell::new(IntrState {
+    depth: 0,
+    sie_before: false,
+});
+
+static INTR_STATE: PerHartIntrState = PerHartIntrState([INTR_ENABLED; MAX_HARTS]);
+
+/// 关闭本hart的中断，可以嵌套，与pop_off配对使用
+pub(super) fn push_off() {
+    let sie = sstatus::read().sie();
+    unsafe {
+        sstatus::clear_sie();
+        let state = &mut *INTR_STATE.0[hart_id()].get();
+        if state.depth == 0 {
+            state.sie_before = sie;
+        }
+        state.depth += 1;
+    }
+}
+
+/// 最外层的pop_off把sstatus.SIE恢复为push_off之前的值
+pub(super) fn pop_off() {
+    assert!(!sstatus::read().sie(), "pop_off with interrupts enabled");
+    unsafe {
+        let state = &mut *INTR_STATE.0[hart_id()].get();
+        assert!(state.depth > 0, "pop_off without matching push_off");
+        state.depth -= 1;
+        if state.depth == 0 && state.sie_before {
+            sstatus::set_sie();
+        }
+    }
+}
+
+/// 持有期间关闭本hart中断的自旋锁
+///
+/// 中断处理程序也会访问的数据必须使用它，否则中断处理程序在同一hart上再次加锁会死锁
+pub struct IrqSafeMutex<T> {
+    inner: SpinMutex<T>,
+}
+
+/// IrqSafeMutex的RAII守卫，drop时先释放锁再恢复中断
+pub struct IrqSafeMutexGuard<'a, T> {
+    guard: ManuallyDrop<SpinMutexGuard<'a, T>>,
+}
+
+impl<T> IrqSafeMutex<T> {
+    pub const fn new(val: T) -> Self {
+        Self {
+            inner: SpinMutex::new(val),
+        }
+    }
+
+    #[track_caller]
+    pub fn lock(&self) -> IrqSafeMutexGuard<'_, T> {
+        push_off();
+        IrqSafeMutexGuard {
+            guard: ManuallyDrop::new(self.inner.lock()),
+        }
+    }
+}
+
+impl<T> Deref for IrqSafeMutexGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for IrqSafeMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T> Drop for IrqSafeMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        unsafe {
+            ManuallyDrop::drop(&mut self.guard);
+        }
+        pop_off();
+    }
+}
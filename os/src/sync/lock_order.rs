@@ -0,0 +1,153 @@
+//! 加锁顺序检查
+//!
+//! 记录每个hart当前持有的锁，获取新锁时为每个已持有的锁登记一条"先A后B"的边。
+//! 如果之前已经登记过相反方向的边，说明两处代码以不一致的顺序获取这两个锁，可能死锁，直接panic。
+//! 锁用其地址标识，只有打开`lock_order`特性时才会检查，否则下面的函数都是空的
+
+#[cfg(not(feature = "lock_order"))]
+#[inline(always)]
+pub fn on_acquire(_lock: usize, _location: &'static core::panic::Location<'static>) {}
+
+#[cfg(not(feature = "lock_order"))]
+#[inline(always)]
+pub fn on_release(_lock: usize) {}
+
+#[cfg(feature = "lock_order")]
+pub use checker::{on_acquire, on_release};
+
+#[cfg(feature = "lock_order")]
+mod checker {
+    use super::super::irq::{pop_off, push_off};
+    use crate::smp::{hart_id, MAX_HARTS};
+    use core::cell::UnsafeCell;
+    use core::hint::spin_loop;
+    use core::panic::Location;
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    /// 每个hart最多同时持有的锁数量
+    const MAX_HELD: usize = 16;
+    /// 最多登记的边数量，登记满后不再检查新的锁对
+    const MAX_EDGES: usize = 256;
+
+    #[derive(Clone, Copy)]
+    struct Held {
+        lock: usize,
+        location: &'static Location<'static>,
+    }
+
+    /// 先获取first，持有它时又获取了second
+    #[derive(Clone, Copy)]
+    struct Edge {
+        first: Held,
+        second: Held,
+    }
+
+    struct HeldLocks {
+        locks: [Option<Held>; MAX_HELD],
+    }
+
+    struct EdgeTable {
+        edges: [Option<Edge>; MAX_EDGES],
+        len: usize,
+    }
+
+    struct Checker {
+        held: [UnsafeCell<HeldLocks>; MAX_HARTS],
+        //不能用SpinMutex保护，否则加锁时会递归进入检查
+        edges_locked: AtomicBool,
+        edges: UnsafeCell<EdgeTable>,
+    }
+
+    unsafe impl Sync for Checker {}
+
+    const NO_HELD_LOCKS: UnsafeCell<HeldLocks> = UnsafeCell::new(HeldLocks {
+        locks: [None; MAX_HELD],
+    });
+
+    static CHECKER: Checker = Checker {
+        held: [NO_HELD_LOCKS; MAX_HARTS],
+        edges_locked: AtomicBool::new(false),
+        edges: UnsafeCell::new(EdgeTable {
+            edges: [None; MAX_EDGES],
+            len: 0,
+        }),
+    };
+
+    fn with_edges<R>(f: impl FnOnce(&mut EdgeTable) -> R) -> R {
+        while CHECKER
+            .edges_locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            spin_loop();
+        }
+        let ret = f(unsafe { &mut *CHECKER.edges.get() });
+        CHECKER.edges_locked.store(false, Ordering::Release);
+        ret
+    }
+
+    /// 成功获取lock之后调用
+    pub fn on_acquire(lock: usize, location: &'static Location<'static>) {
+        //关中断，避免中断处理程序加锁时打乱本hart的持有记录
+        push_off();
+        let held = unsafe { &mut *CHECKER.held[hart_id()].get() };
+        let current = Held { lock, location };
+        for first in held.locks.iter().flatten() {
+            let conflict = with_edges(|table| {
+                let edges = &table.edges[..table.len];
+                if let Some(reversed) = edges
+                    .iter()
+                    .flatten()
+                    .find(|edge| edge.first.lock == lock && edge.second.lock == first.lock)
+                {
+                    return Some(*reversed);
+                }
+                let known = edges
+                    .iter()
+                    .flatten()
+                    .any(|edge| edge.first.lock == first.lock && edge.second.lock == lock);
+                if !known && table.len < MAX_EDGES {
+                    table.edges[table.len] = Some(Edge {
+                        first: *first,
+                        second: current,
+                    });
+                    table.len += 1;
+                }
+                None
+            });
+            if let Some(reversed) = conflict {
+                panic!(
+                    "inconsistent lock order: lock {:#x} taken at {} while holding lock {:#x} (taken at {}), \
+                     but earlier lock {:#x} was taken at {} while holding it (taken at {})",
+                    lock,
+                    location,
+                    first.lock,
+                    first.location,
+                    first.lock,
+                    reversed.second.location,
+                    reversed.first.location
+                );
+            }
+        }
+        match held.locks.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => *slot = Some(current),
+            None => panic!("too many locks held by hart {} at {}", hart_id(), location),
+        }
+        pop_off();
+    }
+
+    /// 释放lock之前调用，释放顺序不必与获取顺序相反
+    pub fn on_release(lock: usize) {
+        push_off();
+        let held = unsafe { &mut *CHECKER.held[hart_id()].get() };
+        if let Some(slot) = held
+            .locks
+            .iter_mut()
+            .rev()
+            .find(|slot| matches!(slot, Some(held) if held.lock == lock))
+        {
+            *slot = None;
+        }
+        pop_off();
+    }
+}
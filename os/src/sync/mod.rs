@@ -1,19 +1,13 @@
-use core::cell::{RefCell, RefMut};
+//! Synchronization primitives
+//!
+//! - `SpinMutex`: 基于原子变量的自旋锁，可以在多个hart之间共享
+//! - `IrqSafeMutex`: 持有期间关闭本hart的中断，防止中断处理程序重复加锁造成死锁
+//!
+//! 打开`lock_order`特性后，`lock_order`模块会检查任意两个锁的加锁顺序是否一致
 
-pub struct UPSafeCell<T> {
-    inner:RefCell<T>
-}
+mod irq;
+mod lock_order;
+mod spin;
 
-unsafe impl <T> Sync for UPSafeCell<T> {}
-
-impl <T> UPSafeCell<T> {
-    pub unsafe fn new(val:T) -> Self{
-        Self {inner:RefCell::new(val)}
-    }
-    
-    pub fn exclusive_borrow(&self) -> RefMut<'_, T>{
-        //缺乏阻塞手段
-        //这里的borrow_mut如果没有被释放就会panic
-        self.inner.borrow_mut()
-    }
-}
\ No newline at end of file
+pub use irq::IrqSafeMutex;
+pub use spin::{SpinMutex, SpinMutexGuard};
@@ -0,0 +1,99 @@
+use super::lock_order;
+use crate::smp::hart_id;
+use core::cell::UnsafeCell;
+use core::hint::spin_loop;
+use core::ops::{Deref, DerefMut};
+use core::panic::Location;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+
+const NO_OWNER: usize = usize::MAX;
+
+/// 自旋锁，拿不到锁时忙等
+pub struct SpinMutex<T> {
+    locked: AtomicBool,
+    //持有锁的hart，同一个hart重复加锁会永远自旋，此时直接panic
+    owner: AtomicUsize,
+    //持有者加锁的位置，只有持有者会写入，为空表示没有记录。
+    //其他hart可能同时读取，所以用原子指针而不是Cell
+    holder: AtomicPtr<Location<'static>>,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for SpinMutex<T> {}
+unsafe impl<T: Send> Send for SpinMutex<T> {}
+
+/// SpinMutex的RAII守卫，drop时释放锁
+pub struct SpinMutexGuard<'a, T> {
+    mutex: &'a SpinMutex<T>,
+}
+
+impl<T> SpinMutex<T> {
+    pub const fn new(val: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            owner: AtomicUsize::new(NO_OWNER),
+            holder: AtomicPtr::new(ptr::null_mut()),
+            data: UnsafeCell::new(val),
+        }
+    }
+
+    #[track_caller]
+    pub fn lock(&self) -> SpinMutexGuard<'_, T> {
+        let caller = Location::caller();
+        if self.owner.load(Ordering::Relaxed) == hart_id() {
+            match unsafe { self.holder.load(Ordering::Relaxed).as_ref() } {
+                Some(holder) => panic!(
+                    "SpinMutex already locked by this hart at {}, locked again at {}",
+                    holder, caller
+                ),
+                None => panic!("SpinMutex already locked by this hart, locked again at {}", caller),
+            }
+        }
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return guard;
+            }
+            //先只读地等待锁被释放，避免反复CAS争抢总线
+            while self.locked.load(Ordering::Relaxed) {
+                spin_loop();
+            }
+        }
+    }
+
+    #[track_caller]
+    pub fn try_lock(&self) -> Option<SpinMutexGuard<'_, T>> {
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| {
+                let caller = Location::caller();
+                lock_order::on_acquire(self as *const _ as usize, caller);
+                self.owner.store(hart_id(), Ordering::Relaxed);
+                self.holder.store(caller as *const _ as *mut _, Ordering::Relaxed);
+                SpinMutexGuard { mutex: self }
+            })
+    }
+}
+
+impl<T> Deref for SpinMutexGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> DerefMut for SpinMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T> Drop for SpinMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.holder.store(ptr::null_mut(), Ordering::Relaxed);
+        self.mutex.owner.store(NO_OWNER, Ordering::Relaxed);
+        lock_order::on_release(self.mutex as *const _ as usize);
+        self.mutex.locked.store(false, Ordering::Release);
+    }
+}
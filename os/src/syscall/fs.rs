@@ -1,19 +1,236 @@
 //! File and filesystem-related syscalls
+//!
+//! 出错时返回负的errno
 
-const FD_STDOUT: usize = 1;
+use crate::fs::{self, link_file, mkdir, open_file, unlink_file, FsError, FsResult, OpenFlags, Stat};
+use crate::task::current_task;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// *at系统调用的dirfd，表示相对于当前目录
+const AT_FDCWD: isize = -100;
+/// 用户传入路径的最大长度
+const PATH_MAX: usize = 256;
+/// dup3的newfd不能超过的上限，避免fd表无限增长
+const FD_MAX: usize = 1024;
+
+/// 把结果转换为系统调用的返回值
+fn syscall_ret(result: FsResult<usize>) -> isize {
+    match result {
+        Ok(value) => value as isize,
+        Err(err) => err.to_errno(),
+    }
+}
+
+/// 读取用户空间以'\0'结尾的字符串，超过PATH_MAX或者不是UTF-8时返回Invalid
+fn user_str(ptr: *const u8) -> FsResult<String> {
+    let mut bytes = Vec::new();
+    for i in 0..PATH_MAX {
+        let byte = unsafe { ptr.add(i).read() };
+        if byte == 0 {
+            return String::from_utf8(bytes).map_err(|_| FsError::Invalid);
+        }
+        bytes.push(byte);
+    }
+    Err(FsError::Invalid)
+}
+
+/// 把相对于dirfd的路径转换为规范化的绝对路径，AT_FDCWD表示相对于当前目录
+///
+/// 规范化时要查找"."和".."之前的目录，不能持有task的锁
+fn user_path_at(dirfd: isize, path: *const u8) -> FsResult<String> {
+    let path = user_str(path)?;
+    if path.starts_with('/') {
+        return fs::normalize("/", &path);
+    }
+    if dirfd == AT_FDCWD {
+        let cwd = current_task().cwd.clone();
+        return fs::normalize(&cwd, &path);
+    }
+    let file = current_task().get_file(dirfd as usize).ok_or(FsError::BadFd)?;
+    let dir = file.dir_path().ok_or(FsError::NotDir)?;
+    fs::normalize(dir, &path)
+}
+
+/// fd对应的文件
+fn get_file(fd: usize) -> FsResult<Arc<dyn fs::File>> {
+    current_task().get_file(fd).ok_or(FsError::BadFd)
+}
+
+/// read from a file with `fd` into buf, blocking on stdin until at least one byte is available
+pub fn sys_read(fd: usize, buf: *mut u8, len: usize) -> isize {
+    //取出文件后释放fd表的锁，读stdin可能阻塞
+    syscall_ret(get_file(fd).and_then(|file| {
+        if !file.readable() {
+            return Err(FsError::BadFd);
+        }
+        file.read(unsafe { core::slice::from_raw_parts_mut(buf, len) })
+    }))
+}
 
 /// write buf of length `len`  to a file with `fd`
 pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
-    match fd {
-        FD_STDOUT => {
-            //直接打印输出
-            let slice = unsafe { core::slice::from_raw_parts(buf, len.clone()) };
-            let str = core::str::from_utf8(slice).unwrap();
-            print!("{}", str);
-            len as isize
+    syscall_ret(get_file(fd).and_then(|file| {
+        if !file.writable() {
+            return Err(FsError::BadFd);
         }
-        _ => {
-            panic!("Unsupported fd in sys_write!");
+        file.write(unsafe { core::slice::from_raw_parts(buf, len) })
+    }))
+}
+
+/// open the file at `path` relative to `dirfd`, returning the new fd
+pub fn sys_openat(dirfd: isize, path: *const u8, flags: u32) -> isize {
+    syscall_ret((|| {
+        let path = user_path_at(dirfd, path)?;
+        let flags = OpenFlags::from_bits(flags).ok_or(FsError::Invalid)?;
+        let file = open_file(&path, flags)?;
+        let mut task = current_task();
+        let fd = task.alloc_fd();
+        task.fd_table[fd] = Some(file);
+        Ok(fd)
+    })())
+}
+
+/// create a new hard link `newpath` to the file `oldpath`
+pub fn sys_linkat(olddirfd: isize, oldpath: *const u8, newdirfd: isize, newpath: *const u8, flags: u32) -> isize {
+    syscall_ret((|| {
+        if flags != 0 {
+            return Err(FsError::Invalid);
         }
+        let oldpath = user_path_at(olddirfd, oldpath)?;
+        let newpath = user_path_at(newdirfd, newpath)?;
+        link_file(&oldpath, &newpath).map(|_| 0)
+    })())
+}
+
+/// remove the link `path`, the file is freed once it has no links and is not open
+///
+/// 不支持删除目录
+pub fn sys_unlinkat(dirfd: isize, path: *const u8, flags: u32) -> isize {
+    syscall_ret((|| {
+        if flags != 0 {
+            return Err(FsError::Invalid);
+        }
+        unlink_file(&user_path_at(dirfd, path)?).map(|_| 0)
+    })())
+}
+
+/// create the directory `path` relative to `dirfd`, `mode` is ignored
+pub fn sys_mkdirat(dirfd: isize, path: *const u8, _mode: u32) -> isize {
+    syscall_ret(user_path_at(dirfd, path).and_then(|path| mkdir(&path)).map(|_| 0))
+}
+
+/// change the current directory to `path`
+pub fn sys_chdir(path: *const u8) -> isize {
+    syscall_ret((|| {
+        let path = user_path_at(AT_FDCWD, path)?;
+        if !fs::resolve(&path)?.1.is_dir() {
+            return Err(FsError::NotDir);
+        }
+        current_task().cwd = path;
+        Ok(0)
+    })())
+}
+
+/// copy the absolute path of the current directory to `buf`
+///
+/// 与Linux的getcwd系统调用一致，返回包括结尾'\0'在内的长度
+pub fn sys_getcwd(buf: *mut u8, len: usize) -> isize {
+    let task = current_task();
+    let path = task.cwd.as_bytes();
+    if path.len() + 1 > len {
+        return FsError::Invalid.to_errno();
+    }
+    let buf = unsafe { core::slice::from_raw_parts_mut(buf, path.len() + 1) };
+    buf[..path.len()].copy_from_slice(path);
+    buf[path.len()] = 0;
+    (path.len() + 1) as isize
+}
+
+/// read directory entries of the directory `fd` into `buf` as `linux_dirent64` records
+pub fn sys_getdents64(fd: usize, buf: *mut u8, len: usize) -> isize {
+    syscall_ret(get_file(fd).and_then(|file| file.getdents(unsafe { core::slice::from_raw_parts_mut(buf, len) })))
+}
+
+/// mount a filesystem of type `fstype` on `target`
+///
+/// 只有一个块设备，source被忽略；data是以逗号分隔的选项，可以为空指针
+pub fn sys_mount(_source: *const u8, target: *const u8, fstype: *const u8, flags: usize, data: *const u8) -> isize {
+    syscall_ret((|| {
+        if flags != 0 {
+            return Err(FsError::Invalid);
+        }
+        let target = user_path_at(AT_FDCWD, target)?;
+        let fstype = user_str(fstype)?;
+        let data = if data.is_null() { None } else { Some(user_str(data)?) };
+        fs::mount_type(&fstype, &target, data.as_deref()).map(|_| 0)
+    })())
+}
+
+/// unmount the filesystem mounted on `target`
+pub fn sys_umount2(target: *const u8, flags: usize) -> isize {
+    syscall_ret((|| {
+        if flags != 0 {
+            return Err(FsError::Invalid);
+        }
+        fs::umount(&user_path_at(AT_FDCWD, target)?).map(|_| 0)
+    })())
+}
+
+/// close the file `fd`
+pub fn sys_close(fd: usize) -> isize {
+    //文件在task的锁释放之后才被drop
+    let file = match current_task().fd_table.get_mut(fd) {
+        Some(slot) => slot.take(),
+        None => None,
+    };
+    match file {
+        Some(file) => {
+            drop(file);
+            0
+        }
+        None => FsError::BadFd.to_errno(),
+    }
+}
+
+/// make `newfd` refer to the same file as `oldfd`, closing the file `newfd` referred to before
+///
+/// 不支持O_CLOEXEC，flags必须为0
+pub fn sys_dup3(oldfd: usize, newfd: usize, flags: u32) -> isize {
+    if flags != 0 || oldfd == newfd {
+        return FsError::Invalid.to_errno();
+    }
+    if newfd >= FD_MAX {
+        return FsError::BadFd.to_errno();
     }
+    //newfd原来的文件在task的锁释放之后才被drop
+    let old_file = {
+        let mut task = current_task();
+        let Some(file) = task.get_file(oldfd) else {
+            return FsError::BadFd.to_errno();
+        };
+        if task.fd_table.len() <= newfd {
+            task.fd_table.resize(newfd + 1, None);
+        }
+        task.fd_table[newfd].replace(file)
+    };
+    drop(old_file);
+    newfd as isize
+}
+
+/// write the status of file `fd` to `st`
+pub fn sys_fstat(fd: usize, st: *mut Stat) -> isize {
+    syscall_ret(get_file(fd).map(|file| {
+        unsafe {
+            *st = file.stat();
+        }
+        0
+    }))
+}
+
+/// write all dirty blocks in the block cache back to the disk
+pub fn sys_sync() -> isize {
+    crate::fs::sync();
+    0
 }
@@ -2,14 +2,61 @@
 
 mod fs;
 mod process;
+mod syslog;
 
+const SYSCALL_GETCWD: usize = 17;
+const SYSCALL_DUP3: usize = 24;
+const SYSCALL_MKDIRAT: usize = 34;
+const SYSCALL_UNLINKAT: usize = 35;
+const SYSCALL_LINKAT: usize = 37;
+const SYSCALL_UMOUNT2: usize = 39;
+const SYSCALL_MOUNT: usize = 40;
+const SYSCALL_CHDIR: usize = 49;
+const SYSCALL_OPENAT: usize = 56;
+const SYSCALL_CLOSE: usize = 57;
+const SYSCALL_GETDENTS64: usize = 61;
+const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
+const SYSCALL_FSTAT: usize = 80;
+const SYSCALL_SYNC: usize = 81;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_SYSLOG: usize = 116;
+const SYSCALL_SHUTDOWN: usize = 2000;
+const SYSCALL_REBOOT: usize = 2001;
 
-pub fn syscall(syscall_number:usize, args:[usize;3]) -> isize {
+pub fn syscall(syscall_number:usize, args:[usize;6]) -> isize {
     match syscall_number {
+        SYSCALL_GETCWD => fs::sys_getcwd(args[0] as *mut u8, args[1]),
+        SYSCALL_DUP3 => fs::sys_dup3(args[0], args[1], args[2] as u32),
+        SYSCALL_MKDIRAT => fs::sys_mkdirat(args[0] as isize, args[1] as *const u8, args[2] as u32),
+        SYSCALL_UNLINKAT => fs::sys_unlinkat(args[0] as isize, args[1] as *const u8, args[2] as u32),
+        SYSCALL_LINKAT => fs::sys_linkat(
+            args[0] as isize,
+            args[1] as *const u8,
+            args[2] as isize,
+            args[3] as *const u8,
+            args[4] as u32,
+        ),
+        SYSCALL_UMOUNT2 => fs::sys_umount2(args[0] as *const u8, args[1]),
+        SYSCALL_MOUNT => fs::sys_mount(
+            args[0] as *const u8,
+            args[1] as *const u8,
+            args[2] as *const u8,
+            args[3],
+            args[4] as *const u8,
+        ),
+        SYSCALL_CHDIR => fs::sys_chdir(args[0] as *const u8),
+        SYSCALL_OPENAT => fs::sys_openat(args[0] as isize, args[1] as *const u8, args[2] as u32),
+        SYSCALL_CLOSE => fs::sys_close(args[0]),
+        SYSCALL_GETDENTS64 => fs::sys_getdents64(args[0], args[1] as *mut u8, args[2]),
+        SYSCALL_READ => fs::sys_read(args[0], args[1] as *mut u8, args[2]),
         SYSCALL_WRITE => fs::sys_write(args[0], args[1] as *const u8, args[2]),
+        SYSCALL_FSTAT => fs::sys_fstat(args[0], args[1] as *mut crate::fs::Stat),
+        SYSCALL_SYNC => fs::sys_sync(),
         SYSCALL_EXIT => process::sys_exit(args[0] as i32),
+        SYSCALL_SYSLOG => syslog::sys_syslog(args[0], args[1] as *mut u8, args[2]),
+        SYSCALL_SHUTDOWN => process::sys_shutdown(args[0] != 0),
+        SYSCALL_REBOOT => process::sys_reboot(args[0] != 0, args[1] != 0),
         _ => panic!("Unsupported syscall_id: {}", syscall_number),
     }
 }
\ No newline at end of file
@@ -1,8 +1,21 @@
 //! App management syscalls
 use crate::batch::run_next_app;
+use log::info;
 
 /// task exits and submit an exit code
 pub fn sys_exit(exit_code: i32) -> ! {
-    println!("[kernel] Application exited with code {}", exit_code);
+    info!("Application exited with code {}", exit_code);
     run_next_app()
 }
+
+/// power off the machine, `failure` selects the reset reason reported to SBI
+pub fn sys_shutdown(failure: bool) -> ! {
+    info!("Application requested shutdown (failure: {})", failure);
+    crate::power::shutdown(failure)
+}
+
+/// reboot the machine, cold reboot unless `warm` is set
+pub fn sys_reboot(warm: bool, failure: bool) -> ! {
+    info!("Application requested {} reboot", if warm { "warm" } else { "cold" });
+    crate::power::reboot(warm, failure)
+}
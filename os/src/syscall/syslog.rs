@@ -0,0 +1,37 @@
+//! Kernel log syscalls
+
+use crate::logging::{clear_log_buffer, log_unread_len, read_log_buffer, read_log_unread, LOG_BUFFER_SIZE};
+
+/// 从读指针开始读取未读的日志
+const SYSLOG_ACTION_READ: usize = 2;
+/// 读取所有日志，不移动读指针
+const SYSLOG_ACTION_READ_ALL: usize = 3;
+/// 读取所有日志后清空
+const SYSLOG_ACTION_READ_CLEAR: usize = 4;
+/// 清空日志
+const SYSLOG_ACTION_CLEAR: usize = 5;
+/// 查询未读的日志字节数
+const SYSLOG_ACTION_SIZE_UNREAD: usize = 9;
+/// 查询日志缓冲区大小
+const SYSLOG_ACTION_SIZE_BUFFER: usize = 10;
+
+/// operate on the kernel log ring buffer, actions follow Linux syslog(2)
+pub fn sys_syslog(action: usize, buf: *mut u8, len: usize) -> isize {
+    match action {
+        SYSLOG_ACTION_READ => {
+            let buf = unsafe { core::slice::from_raw_parts_mut(buf, len) };
+            read_log_unread(buf) as isize
+        }
+        SYSLOG_ACTION_READ_ALL | SYSLOG_ACTION_READ_CLEAR => {
+            let buf = unsafe { core::slice::from_raw_parts_mut(buf, len) };
+            read_log_buffer(buf, action == SYSLOG_ACTION_READ_CLEAR) as isize
+        }
+        SYSLOG_ACTION_CLEAR => {
+            clear_log_buffer();
+            0
+        }
+        SYSLOG_ACTION_SIZE_UNREAD => log_unread_len() as isize,
+        SYSLOG_ACTION_SIZE_BUFFER => LOG_BUFFER_SIZE as isize,
+        _ => -1,
+    }
+}
@@ -0,0 +1,78 @@
+//! 每个hart上正在运行的app的状态
+//!
+//! 批处理系统中每个hart同一时刻只运行一个app，app的文件描述符表等状态按hart保存，
+//! 加载下一个app时重置，上一个app没有关闭的文件在这里被关闭
+
+use crate::fs::{File, Stdin, Stdout};
+use crate::smp::{hart_id, MAX_HARTS};
+use crate::sync::{SpinMutex, SpinMutexGuard};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+pub struct TaskControlBlock {
+    /// 文件描述符表，下标为fd，关闭的fd为None
+    pub fd_table: Vec<Option<Arc<dyn File>>>,
+    /// 当前目录的绝对路径，已经规范化
+    pub cwd: String,
+}
+
+impl TaskControlBlock {
+    const fn empty() -> Self {
+        Self {
+            fd_table: Vec::new(),
+            cwd: String::new(),
+        }
+    }
+
+    /// 新app的初始状态，打开stdin、stdout和stderr，当前目录为根目录
+    fn reset(&mut self) {
+        self.cwd = String::from("/");
+        self.fd_table = vec![
+            // 0 -> stdin
+            Some(Arc::new(Stdin)),
+            // 1 -> stdout
+            Some(Arc::new(Stdout)),
+            // 2 -> stderr
+            Some(Arc::new(Stdout)),
+        ];
+    }
+
+    /// 分配编号最小的空闲fd
+    pub fn alloc_fd(&mut self) -> usize {
+        if let Some(fd) = (0..self.fd_table.len()).find(|&fd| self.fd_table[fd].is_none()) {
+            fd
+        } else {
+            self.fd_table.push(None);
+            self.fd_table.len() - 1
+        }
+    }
+
+    /// fd对应的文件
+    pub fn get_file(&self, fd: usize) -> Option<Arc<dyn File>> {
+        self.fd_table.get(fd).cloned().flatten()
+    }
+}
+
+const EMPTY_TASK: SpinMutex<TaskControlBlock> = SpinMutex::new(TaskControlBlock::empty());
+static TASKS: [SpinMutex<TaskControlBlock>; MAX_HARTS] = [EMPTY_TASK; MAX_HARTS];
+
+/// 当前hart上运行的app
+///
+/// 读写文件可能阻塞或者访问磁盘，应当先取出文件再释放守卫
+pub fn current_task() -> SpinMutexGuard<'static, TaskControlBlock> {
+    TASKS[hart_id()].lock()
+}
+
+/// 为下一个app重置当前hart的状态
+pub fn reset_current_task() {
+    // 先从锁中取出旧的文件描述符表，关闭文件时不持有锁
+    let old_fd_table = {
+        let mut task = current_task();
+        let old = core::mem::take(&mut task.fd_table);
+        task.reset();
+        old
+    };
+    drop(old_fd_table);
+}
@@ -1,6 +1,8 @@
 use riscv::register::sstatus::{self, Sstatus, SPP};
 /// Trap Context
-#[repr(C)]
+///
+/// 共68个槽位，按16字节对齐，保证__alltraps在内核栈上分配TrapContext后sp仍满足调用约定
+#[repr(C, align(16))]
 pub struct TrapContext {
     /// general regs[0..31]
     pub x: [usize; 32],
@@ -8,6 +10,12 @@ pub struct TrapContext {
     pub sstatus: Sstatus,
     /// CSR sepc
     pub sepc: usize,
+    /// float regs[0..31]，以原始位模式保存
+    pub f: [usize; 32],
+    /// CSR fcsr
+    pub fcsr: usize,
+    /// 内核态的tp，即当前hart的id，由__restore保存，trap进入内核时恢复
+    pub kernel_tp: usize,
 }
 
 impl TrapContext {
@@ -15,6 +23,10 @@ impl TrapContext {
     pub fn set_sp(&mut self, sp: usize) {
         self.x[2] = sp;
     }
+    /// set thread pointer to x_4 reg (tp)
+    pub fn set_tp(&mut self, tp: usize) {
+        self.x[4] = tp;
+    }
     /// init app context
     /// entry:app入口地址
     /// sp：app用户栈地址
@@ -25,6 +37,10 @@ impl TrapContext {
             x: [0; 32],
             sstatus,
             sepc: entry, // entry point of app
+            // 新app的浮点寄存器全部清零，不会看到上一个app留下的值
+            f: [0; 32],
+            fcsr: 0,
+            kernel_tp: 0,
         };
         cx.set_sp(sp); // app's user stack pointer
         cx // return initial Trap Context of app
@@ -17,9 +17,12 @@ mod context;
 use crate::batch::run_next_app;
 use crate::syscall::syscall;
 use core::arch::global_asm;
+use log::error;
 use riscv::register::{
     mtvec::TrapMode,
-    scause::{self, Exception, Trap},
+    scause::{self, Exception, Interrupt, Trap},
+    sie,
+    sstatus::{self, FS},
     stval, stvec,
 };
 
@@ -33,6 +36,11 @@ pub fn init() {
     }
     unsafe {
         stvec::write(__alltraps as usize, TrapMode::Direct);
+        // 开启浮点单元，否则trap.S中保存/恢复浮点寄存器的指令会触发非法指令异常
+        sstatus::set_fs(FS::Initial);
+        // 内核态始终关中断，外部中断和核间中断只在运行app时打断它
+        sie::set_sext();
+        sie::set_ssoft();
     }
 }
 
@@ -44,14 +52,23 @@ pub fn trap_handler(cx: &mut TrapContext) -> &mut TrapContext {
     match scause.cause() {
         Trap::Exception(Exception::UserEnvCall) => {
             cx.sepc += 4;
-            cx.x[10] = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12]]) as usize;
+            cx.x[10] = syscall(
+                cx.x[17],
+                [cx.x[10], cx.x[11], cx.x[12], cx.x[13], cx.x[14], cx.x[15]],
+            ) as usize;
+        }
+        Trap::Interrupt(Interrupt::SupervisorExternal) => {
+            crate::drivers::handle_external_interrupt();
+        }
+        Trap::Interrupt(Interrupt::SupervisorSoft) => {
+            crate::smp::clear_ipi();
         }
         Trap::Exception(Exception::StoreFault) | Trap::Exception(Exception::StorePageFault) => {
-            println!("[kernel] PageFault in application, kernel killed it.");
+            error!("PageFault in application, kernel killed it.");
             run_next_app();
         }
         Trap::Exception(Exception::IllegalInstruction) => {
-            println!("[kernel] IllegalInstruction in application, kernel killed it.");
+            error!("IllegalInstruction in application, kernel killed it.");
             run_next_app();
         }
         _ => {
@@ -0,0 +1,18 @@
+# 每个app链接到不同的地址: 0x80400000 + app_id * 0x20000，多个hart可以同时运行不同的app
+# 与os/src/batch.rs中的APP_BASE_ADDRESS和APP_MAX_SIZE保持一致
+# 地址通过--defsym传给链接器，链接参数不同时cargo会重新链接，不修改src/linker.ld
+import os
+import subprocess
+import sys
+
+base_address = 0x80400000
+step = 0x20000
+
+apps = sorted(app[:app.find('.')] for app in os.listdir('src/bin') if app.endswith('.rs'))
+for app_id, app in enumerate(apps):
+    address = hex(base_address + step * app_id)
+    cmd = ['cargo', 'rustc', '--bin', app, '--release', '--',
+           '-C', 'link-arg=--defsym=BASE_ADDRESS=%s' % address]
+    if subprocess.call(cmd) != 0:
+        sys.exit('[build.py] failed to build application %s' % app)
+    print('[build.py] application %s start with address %s' % (app, address))
@@ -0,0 +1,104 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::arch::asm;
+use core::hint::black_box;
+
+const SYSCALL_WRITE: usize = 64;
+
+/// 把f0~f31和fcsr全部写入指定值，执行一次系统调用(trap进内核)，再读回来
+fn fp_regs_across_ecall(src: &[f64; 32], dst: &mut [f64; 32], frm: usize) -> usize {
+    let fcsr: usize;
+    unsafe {
+        asm!(
+            ".irp n, 0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31",
+            "fld f\\n, \\n*8(a1)",
+            ".endr",
+            "fsrm t1",
+            // sys_write(1, src, 0)，只为了陷入内核；trap前后t0/t1等寄存器都由内核保存恢复
+            "ecall",
+            ".irp n, 0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31",
+            "fsd f\\n, \\n*8(t0)",
+            ".endr",
+            "frcsr a0",
+            in("a1") src.as_ptr(),
+            in("t0") dst.as_mut_ptr(),
+            in("t1") frm,
+            in("a2") 0,
+            in("a7") SYSCALL_WRITE,
+            inlateout("a0") 1usize => fcsr,
+            // fs0~fs11是callee-saved，clobber_abi不会覆盖，需要单独声明
+            out("f8") _, out("f9") _,
+            out("f18") _, out("f19") _, out("f20") _, out("f21") _, out("f22") _,
+            out("f23") _, out("f24") _, out("f25") _, out("f26") _, out("f27") _,
+            clobber_abi("C"),
+        );
+    }
+    fcsr
+}
+
+/// 读取app刚开始运行时的浮点寄存器
+fn initial_fp_regs(dst: &mut [u64; 32]) {
+    unsafe {
+        asm!(
+            ".irp n, 0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31",
+            "fsd f\\n, \\n*8({dst})",
+            ".endr",
+            dst = in(reg) dst.as_mut_ptr(),
+        );
+    }
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    // 内核为每个app清零浮点寄存器，上一个app的值不应泄漏过来
+    let mut initial = [u64::MAX; 32];
+    initial_fp_regs(&mut initial);
+    println!("Test fp_regs: f0-f31 and fcsr should survive traps");
+    if initial.iter().any(|&bits| bits != 0) {
+        println!("fp registers are not cleared for a new app: {:?}", initial);
+        return -1;
+    }
+
+    for round in 0..4usize {
+        let mut src = [0f64; 32];
+        for (i, v) in src.iter_mut().enumerate() {
+            *v = (round * 32 + i) as f64 * 1.5 + 0.25;
+        }
+        let mut dst = [0f64; 32];
+        // frm依次取RNE/RTZ/RDN/RUP
+        let frm = round;
+        let fcsr = fp_regs_across_ecall(&src, &mut dst, frm);
+        for i in 0..32 {
+            if src[i].to_bits() != dst[i].to_bits() {
+                println!("round {}: f{} = {} after trap, expected {}", round, i, dst[i], src[i]);
+                return -1;
+            }
+        }
+        if (fcsr >> 5) & 0x7 != frm {
+            println!("round {}: fcsr.frm = {} after trap, expected {}", round, (fcsr >> 5) & 0x7, frm);
+            return -1;
+        }
+    }
+
+    // 编译器生成的浮点运算中间穿插系统调用，black_box防止整个循环被常量折叠
+    let factor = black_box(1.25f64);
+    let mut acc = 1.0f64;
+    for i in 1..=20 {
+        acc = acc * factor + 1.0 / i as f64;
+        println!("step {}: acc = {}", i, acc as i64);
+    }
+    let mut expect = 1.0f64;
+    for i in 1..=20 {
+        expect = expect * factor + 1.0 / i as f64;
+    }
+    if acc.to_bits() != expect.to_bits() {
+        println!("accumulated value changed across syscalls");
+        return -1;
+    }
+    println!("Test fp_regs OK!");
+    0
+}
@@ -0,0 +1,63 @@
+#![no_std]
+#![no_main]
+#![feature(thread_local)]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::arch::asm;
+use core::cell::Cell;
+
+/// 位于.tdata，需要内核从PT_TLS段复制初始值
+#[thread_local]
+static COUNTER: Cell<usize> = Cell::new(0x1234_5678);
+
+const ZERO: Cell<usize> = Cell::new(0);
+
+/// 位于.tbss，需要内核清零
+#[thread_local]
+static HISTORY: [Cell<usize>; 16] = [ZERO; 16];
+
+fn read_tp() -> usize {
+    let tp: usize;
+    unsafe {
+        asm!("mv {}, tp", out(reg) tp);
+    }
+    tp
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let tp = read_tp();
+    println!("Test thread_local: tp = {:#x}", tp);
+    if tp == 0 {
+        println!("tp is not set up by the kernel");
+        return -1;
+    }
+    if COUNTER.get() != 0x1234_5678 {
+        println!("COUNTER = {:#x}, .tdata is not initialized", COUNTER.get());
+        return -1;
+    }
+    if HISTORY.iter().any(|v| v.get() != 0) {
+        println!(".tbss is not zeroed");
+        return -1;
+    }
+    for (i, slot) in HISTORY.iter().enumerate() {
+        COUNTER.set(COUNTER.get() + 1);
+        slot.set(COUNTER.get());
+        // 每次println都会trap进内核，tp必须原样恢复
+        println!("step {}: COUNTER = {:#x}", i, COUNTER.get());
+    }
+    for (i, slot) in HISTORY.iter().enumerate() {
+        if slot.get() != 0x1234_5678 + i + 1 {
+            println!("HISTORY[{}] = {:#x} is corrupted", i, slot.get());
+            return -1;
+        }
+    }
+    if read_tp() != tp {
+        println!("tp changed across traps");
+        return -1;
+    }
+    println!("Test thread_local OK!");
+    0
+}
@@ -0,0 +1,34 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{dmesg, dmesg_buffer_size};
+
+const BUF_SIZE: usize = 16 * 1024;
+
+/// 用户栈只有8K，缓冲区放在.bss中
+static mut BUF: [u8; BUF_SIZE] = [0; BUF_SIZE];
+
+#[no_mangle]
+fn main() -> i32 {
+    let buf = unsafe { &mut *core::ptr::addr_of_mut!(BUF) };
+    println!("kernel log buffer size: {}", dmesg_buffer_size());
+    let len = dmesg(buf, false);
+    if len < 0 {
+        println!("dmesg failed: {}", len);
+        return -1;
+    }
+    let log = &buf[..len as usize];
+    // 缓冲区满时最旧的记录可能从一个多字节字符中间截断，跳过开头的UTF-8后续字节
+    let start = log.iter().position(|&b| b & 0xc0 != 0x80).unwrap_or(log.len());
+    print!("{}", core::str::from_utf8(&log[start..]).unwrap_or("<invalid utf-8>\n"));
+    // 内核在运行本app之前应当记录了加载它的日志
+    if !log.windows(b"Loading app_".len()).any(|w| w == b"Loading app_") {
+        println!("no loading record in kernel log");
+        return -1;
+    }
+    println!("Test dmesg OK!");
+    0
+}
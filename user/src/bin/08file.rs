@@ -0,0 +1,63 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::errno::{EBADF, ENAMETOOLONG, ENOENT};
+use user_lib::{close, fstat, open, read, unlink, write, OpenFlags, Stat, StatMode};
+
+const TEXT: &str = "Hello, file!\n";
+
+#[no_mangle]
+fn main() -> i32 {
+    let fd = open("filea\0", OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    assert_eq!(write(fd, TEXT.as_bytes()), TEXT.len() as isize);
+    // 只写打开的文件不能读
+    assert_eq!(read(fd, &mut [0u8; 1]), -EBADF);
+    close(fd);
+
+    let fd = open("filea\0", OpenFlags::WRONLY | OpenFlags::APPEND);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    assert_eq!(write(fd, TEXT.as_bytes()), TEXT.len() as isize);
+    close(fd);
+
+    let fd = open("filea\0", OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    let mut stat = Stat::new();
+    assert_eq!(fstat(fd, &mut stat), 0);
+    assert_eq!(stat.mode, StatMode::FILE);
+    assert_eq!(stat.nlink, 1);
+    assert_eq!(stat.size, 2 * TEXT.len() as u64);
+    let mut buf = [0u8; 64];
+    let len = read(fd, &mut buf) as usize;
+    assert_eq!(len, 2 * TEXT.len());
+    assert_eq!(&buf[..TEXT.len()], TEXT.as_bytes());
+    assert_eq!(&buf[TEXT.len()..len], TEXT.as_bytes());
+    // 读到文件末尾
+    assert_eq!(read(fd, &mut buf), 0);
+    close(fd);
+    assert_eq!(close(fd), -EBADF);
+    assert_eq!(open("nonexistent\0", OpenFlags::RDONLY), -ENOENT);
+    // easy-fs的文件名最多27字节，更长的名字不会被截断，28字节的名字截断后会从é的中间断开
+    for name in [
+        "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\0",
+        "éééééééééééééé\0",
+    ] {
+        match open(name, OpenFlags::CREATE | OpenFlags::WRONLY) {
+            // 根目录不是easy-fs时没有这个限制
+            fd if fd >= 0 => {
+                close(fd as usize);
+                assert_eq!(unlink(name), 0);
+            }
+            err => assert_eq!(err, -ENAMETOOLONG),
+        }
+    }
+    print!("ino {} size {}:\n{}", stat.ino, stat.size, core::str::from_utf8(&buf[..len]).unwrap());
+    println!("Test file OK!");
+    0
+}
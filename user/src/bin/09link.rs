@@ -0,0 +1,51 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::errno::{EEXIST, ENOENT};
+use user_lib::{close, fstat, link, open, read, unlink, write, OpenFlags, Stat};
+
+const TEXT: &str = "linked data\n";
+
+fn nlink(fd: usize) -> u32 {
+    let mut stat = Stat::new();
+    assert_eq!(fstat(fd, &mut stat), 0);
+    stat.nlink
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let fd = open("link_a\0", OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    write(fd, TEXT.as_bytes());
+    assert_eq!(nlink(fd), 1);
+    // 上一次测试中断时可能留下link_b
+    unlink("link_b\0");
+    assert_eq!(link("link_a\0", "link_b\0"), 0);
+    assert_eq!(nlink(fd), 2);
+    // 新名字已经存在
+    assert_eq!(link("link_a\0", "link_b\0"), -EEXIST);
+    close(fd);
+
+    assert_eq!(unlink("link_a\0"), 0);
+    assert!(open("link_a\0", OpenFlags::RDONLY) < 0);
+    let fd = open("link_b\0", OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    assert_eq!(nlink(fd), 1);
+
+    // 删除最后一个链接后，已经打开的文件仍然可以读
+    assert_eq!(unlink("link_b\0"), 0);
+    assert_eq!(unlink("link_b\0"), -ENOENT);
+    assert_eq!(nlink(fd), 0);
+    let mut buf = [0u8; 32];
+    let len = read(fd, &mut buf) as usize;
+    assert_eq!(&buf[..len], TEXT.as_bytes());
+    close(fd);
+    assert!(open("link_b\0", OpenFlags::RDONLY) < 0);
+    println!("Test link OK!");
+    0
+}
@@ -0,0 +1,75 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::errno::{EEXIST, EISDIR, ENOENT, ENOTDIR};
+use user_lib::{chdir, close, getcwd, getdents, mkdir, open, parse_dirents, read, write, OpenFlags};
+
+/// 列出目录中的所有名字，返回其中是否有name以及它是否为目录
+fn find_in_dir(path: &str, name: &str) -> Option<bool> {
+    let fd = open(path, OpenFlags::RDONLY | OpenFlags::DIRECTORY);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    let mut buf = [0u8; 256];
+    let mut found = None;
+    println!("{}:", path.trim_end_matches('\0'));
+    loop {
+        let len = getdents(fd, &mut buf);
+        assert!(len >= 0);
+        if len == 0 {
+            break;
+        }
+        for dirent in parse_dirents(&buf, len as usize) {
+            println!("  {:>4} {}{}", dirent.ino, dirent.name, if dirent.is_dir { "/" } else { "" });
+            if dirent.name == name {
+                found = Some(dirent.is_dir);
+            }
+        }
+    }
+    close(fd);
+    found
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut cwd = [0u8; 64];
+    assert_eq!(getcwd(&mut cwd), Some("/"));
+    // 目录可能在上一次启动时已经创建，没有rmdir，存在时mkdir失败
+    mkdir("dir_test\0");
+    mkdir("dir_test/sub\0");
+    assert_eq!(mkdir("dir_test\0"), -EEXIST);
+    assert_eq!(find_in_dir("/\0", "dir_test"), Some(true));
+
+    assert_eq!(chdir("dir_test/sub\0"), 0);
+    assert_eq!(getcwd(&mut cwd), Some("/dir_test/sub"));
+    let fd = open("../file\0", OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC);
+    assert!(fd >= 0);
+    write(fd as usize, b"in dir_test\n");
+    close(fd as usize);
+
+    assert_eq!(chdir("..\0"), 0);
+    assert_eq!(getcwd(&mut cwd), Some("/dir_test"));
+    assert_eq!(find_in_dir(".\0", "file"), Some(false));
+    assert_eq!(find_in_dir(".\0", "sub"), Some(true));
+    assert_eq!(find_in_dir("sub\0", ".."), Some(true));
+    let fd = open("/dir_test/sub/../file\0", OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let mut buf = [0u8; 32];
+    let len = read(fd as usize, &mut buf) as usize;
+    assert_eq!(&buf[..len], b"in dir_test\n");
+    close(fd as usize);
+    // 文件不能作为路径中间的目录，目录不能写
+    assert_eq!(open("file/x\0", OpenFlags::RDONLY), -ENOTDIR);
+    assert_eq!(open("sub\0", OpenFlags::WRONLY), -EISDIR);
+    assert_eq!(chdir("file\0"), -ENOTDIR);
+    // ".."之前的部分必须是存在的目录，不能按字面消去
+    assert_eq!(open("file/../sub\0", OpenFlags::RDONLY), -ENOTDIR);
+    assert_eq!(chdir("missing/..\0"), -ENOENT);
+
+    assert_eq!(chdir("/\0"), 0);
+    assert_eq!(getcwd(&mut cwd), Some("/"));
+    println!("Test dir OK!");
+    0
+}
@@ -0,0 +1,64 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::errno::{EBUSY, EINVAL, ENODEV, ENOTDIR};
+use user_lib::{chdir, close, fstat, getcwd, mkdir, mount, open, read, umount, write, OpenFlags, Stat};
+
+const TEXT: &str = "seen through the mount\n";
+
+fn stat_of(path: &str) -> Stat {
+    let fd = open(path, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let mut stat = Stat::new();
+    assert_eq!(fstat(fd as usize, &mut stat), 0);
+    close(fd as usize);
+    stat
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    // 挂载点可能在上一次启动时已经创建
+    mkdir("/mnt_test\0");
+    let fd = open("/mount_file\0", OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC);
+    assert!(fd >= 0);
+    write(fd as usize, TEXT.as_bytes());
+    close(fd as usize);
+
+    assert_eq!(mount("none\0", "/mnt_test\0", "nofs\0", None), -ENODEV);
+    assert_eq!(mount("none\0", "/mount_file\0", "tmpfs\0", None), -ENOTDIR);
+    // 把根目录的easy-fs再挂载一次，两处看到的是同一个文件系统
+    match mount("none\0", "/mnt_test\0", "easyfs\0", None) {
+        0 => {}
+        err if err == -ENODEV => {
+            println!("no disk, mount test skipped");
+            return 0;
+        }
+        err => panic!("mount easyfs failed: {}", err),
+    }
+    assert_eq!(mount("none\0", "/mnt_test\0", "easyfs\0", None), -EBUSY);
+    let fd = open("/mnt_test/mount_file\0", OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let mut buf = [0u8; 64];
+    let len = read(fd as usize, &mut buf) as usize;
+    assert_eq!(&buf[..len], TEXT.as_bytes());
+    close(fd as usize);
+    let outer = stat_of("/mount_file\0");
+    let inner = stat_of("/mnt_test/mount_file\0");
+    assert_eq!((outer.dev, outer.ino), (inner.dev, inner.ino));
+
+    // ".."从挂载点回到父文件系统
+    let mut cwd = [0u8; 64];
+    assert_eq!(chdir("/mnt_test\0"), 0);
+    assert_eq!(chdir("..\0"), 0);
+    assert_eq!(getcwd(&mut cwd), Some("/"));
+
+    assert_eq!(umount("/\0"), -EBUSY);
+    assert_eq!(umount("/mnt_test\0"), 0);
+    assert_eq!(umount("/mnt_test\0"), -EINVAL);
+    assert!(open("/mnt_test/mount_file\0", OpenFlags::RDONLY) < 0);
+    println!("Test mount OK!");
+    0
+}
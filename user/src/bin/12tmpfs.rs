@@ -0,0 +1,79 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::errno::{EBUSY, EINVAL, ENOSPC, EXDEV};
+use user_lib::{close, fstat, link, mkdir, mount, open, read, umount, unlink, write, OpenFlags, Stat};
+
+const TEXT: &str = "kept in memory\n";
+
+fn dev_of(path: &str) -> u64 {
+    let fd = open(path, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let mut stat = Stat::new();
+    assert_eq!(fstat(fd as usize, &mut stat), 0);
+    close(fd as usize);
+    stat.dev
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    // 启动时挂载的/tmp
+    let fd = open("/tmp/tmpfs_file\0", OpenFlags::CREATE | OpenFlags::RDWR | OpenFlags::TRUNC);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    assert_eq!(write(fd, TEXT.as_bytes()), TEXT.len() as isize);
+    close(fd);
+    let fd = open("/tmp/tmpfs_file\0", OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let mut buf = [0u8; 32];
+    let len = read(fd as usize, &mut buf) as usize;
+    assert_eq!(&buf[..len], TEXT.as_bytes());
+    close(fd as usize);
+    assert_ne!(dev_of("/tmp/tmpfs_file\0"), dev_of("/\0"));
+    assert_eq!(link("/tmp/tmpfs_file\0", "/tmpfs_link\0"), -EXDEV);
+    assert_eq!(unlink("/tmp/tmpfs_file\0"), 0);
+
+    // 容量只有两页的tmpfs
+    assert_eq!(mkdir("/tmp/small\0"), 0);
+    assert_eq!(mount("none\0", "/tmp/small\0", "tmpfs\0", Some("size=1x\0")), -EINVAL);
+    assert_eq!(mount("none\0", "/tmp/small\0", "tmpfs\0", Some("size=8k\0")), 0);
+    let fd = open("/tmp/small/big\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    let data = [0x5au8; 3000];
+    assert_eq!(write(fd, &data), 3000);
+    assert_eq!(write(fd, &data), 3000);
+    assert_eq!(write(fd, &data), 8192 - 6000);
+    assert_eq!(write(fd, &data), -ENOSPC);
+    assert_eq!(umount("/tmp/small\0"), -EBUSY);
+    close(fd);
+
+    // 截断后页被归还
+    let fd = open("/tmp/small/big\0", OpenFlags::WRONLY | OpenFlags::TRUNC);
+    assert!(fd >= 0);
+    assert_eq!(write(fd as usize, &data), 3000);
+    close(fd as usize);
+    assert_eq!(umount("/tmp/small\0"), 0);
+
+    // 所有tmpfs一起受内核堆的预算限制，size再大写满时也只是返回ENOSPC
+    assert_eq!(mount("none\0", "/tmp/small\0", "tmpfs\0", Some("size=16m\0")), 0);
+    let fd = open("/tmp/small/big\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    let mut total = 0;
+    loop {
+        let len = write(fd as usize, &data);
+        if len < 0 {
+            assert_eq!(len, -ENOSPC);
+            break;
+        }
+        total += len as usize;
+    }
+    assert!(total < 512 * 1024);
+    close(fd as usize);
+    assert_eq!(umount("/tmp/small\0"), 0);
+    println!("Test tmpfs OK!");
+    0
+}
@@ -0,0 +1,71 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::errno::{EBADF, EPERM};
+use user_lib::{close, dup2, fstat, getdents, open, parse_dirents, read, unlink, write, OpenFlags, Stat, StatMode};
+
+fn open_device(path: &str, flags: OpenFlags) -> usize {
+    let fd = open(path, flags);
+    assert!(fd >= 0);
+    fd as usize
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let null = open_device("/dev/null\0", OpenFlags::RDWR | OpenFlags::TRUNC);
+    assert_eq!(write(null, b"discarded\n"), 10);
+    assert_eq!(read(null, &mut [0u8; 8]), 0);
+    let mut stat = Stat::new();
+    assert_eq!(fstat(null, &mut stat), 0);
+    assert_eq!(stat.mode, StatMode::CHR);
+    close(null);
+
+    let zero = open_device("/dev/zero\0", OpenFlags::RDONLY);
+    let mut buf = [0xffu8; 16];
+    assert_eq!(read(zero, &mut buf), 16);
+    assert_eq!(buf, [0u8; 16]);
+    close(zero);
+
+    let urandom = open_device("/dev/urandom\0", OpenFlags::RDONLY);
+    let mut first = [0u8; 16];
+    let mut second = [0u8; 16];
+    assert_eq!(read(urandom, &mut first), 16);
+    assert_eq!(read(urandom, &mut second), 16);
+    assert_ne!(first, second);
+    close(urandom);
+
+    // 输出重定向到控制台设备
+    let console = open_device("/dev/console\0", OpenFlags::WRONLY);
+    assert_eq!(write(console, b"written to /dev/console\n"), 24);
+    close(console);
+
+    // 把stdout重定向到/dev/null，之后再恢复
+    let saved = 10;
+    assert_eq!(dup2(1, saved), saved as isize);
+    let null = open_device("/dev/null\0", OpenFlags::WRONLY);
+    assert_eq!(dup2(null, 1), 1);
+    close(null);
+    println!("this line goes to /dev/null");
+    assert_eq!(write(1, b"discarded\n"), 10);
+    assert_eq!(dup2(saved, 1), 1);
+    close(saved);
+    assert_eq!(dup2(saved, 1), -EBADF);
+
+    let dir = open_device("/dev\0", OpenFlags::RDONLY | OpenFlags::DIRECTORY);
+    let mut dirents = [0u8; 256];
+    let len = getdents(dir, &mut dirents);
+    assert!(len > 0);
+    let count = parse_dirents(&dirents, len as usize)
+        .filter(|dirent| ["null", "zero", "console", "urandom"].contains(&dirent.name))
+        .count();
+    assert_eq!(count, 4);
+    close(dir);
+    // 不能在devfs中创建或删除文件
+    assert_eq!(open("/dev/file\0", OpenFlags::CREATE | OpenFlags::WRONLY), -EPERM);
+    assert_eq!(unlink("/dev/null\0"), -EPERM);
+    println!("Test devfs OK!");
+    0
+}
@@ -0,0 +1,44 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, fstat, open, read, write, OpenFlags, Stat};
+
+/// user/data/hello.txt的内容
+const HELLO: &[u8] = b"Hello from the initramfs!\n";
+
+fn read_file(path: &str, buf: &mut [u8]) -> usize {
+    let fd = open(path, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let len = read(fd as usize, buf);
+    assert!(len >= 0);
+    close(fd as usize);
+    len as usize
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut buf = [0u8; 64];
+    let len = read_file("/initramfs/data/hello.txt\0", &mut buf);
+    assert_eq!(&buf[..len], HELLO);
+
+    // app也可以按路径找到
+    let len = read_file("/initramfs/bin/14initramfs\0", &mut buf[..4]);
+    assert_eq!(&buf[..len], b"\x7fELF");
+
+    // 修改initramfs中的文件不影响内核镜像中的归档，只是先复制一份
+    let fd = open("/initramfs/data/hello.txt\0", OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    assert_eq!(write(fd as usize, b"J"), 1);
+    let mut stat = Stat::new();
+    assert_eq!(fstat(fd as usize, &mut stat), 0);
+    assert_eq!(stat.size, HELLO.len() as u64);
+    close(fd as usize);
+    let len = read_file("/initramfs/data/hello.txt\0", &mut buf);
+    assert_eq!(&buf[..1], b"J");
+    assert_eq!(&buf[1..len], &HELLO[1..]);
+    println!("Test initramfs OK!");
+    0
+}
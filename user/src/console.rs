@@ -1,6 +1,6 @@
 use core::fmt;
 use core::fmt::{Write, Arguments, Result};
-use crate::syscall::sys_write;
+use crate::syscall::{sys_read, sys_write};
 
 struct Stdout;
 
@@ -17,6 +17,15 @@ pub fn print(args: Arguments) {
     Stdout.write_fmt(args).unwrap();
 }
 
+const STDIN: usize = 0;
+
+/// 从控制台读取一个字节，没有输入时阻塞
+pub fn getchar() -> u8 {
+    let mut c = [0u8; 1];
+    sys_read(STDIN, &mut c);
+    c[0]
+}
+
 #[macro_export]
 macro_rules! print {
     ($fmt: literal $(, $($arg: tt)+)?) => {
@@ -5,6 +5,8 @@
 
 #[macro_use]
 pub mod console;
+#[macro_use]
+extern crate bitflags;
 mod lang_items;
 mod syscall;
 
@@ -33,9 +35,215 @@ fn clear_bss() {
 
 use syscall::*;
 
+const SYSLOG_ACTION_READ_ALL: usize = 3;
+const SYSLOG_ACTION_READ_CLEAR: usize = 4;
+const SYSLOG_ACTION_SIZE_BUFFER: usize = 10;
+
+/// 文件系统调用失败时返回的负errno，取值与Linux一致
+pub mod errno {
+    pub const EPERM: isize = 1;
+    pub const ENOENT: isize = 2;
+    pub const EBADF: isize = 9;
+    pub const EBUSY: isize = 16;
+    pub const EEXIST: isize = 17;
+    pub const EXDEV: isize = 18;
+    pub const ENODEV: isize = 19;
+    pub const ENOTDIR: isize = 20;
+    pub const EISDIR: isize = 21;
+    pub const EINVAL: isize = 22;
+    pub const ENOSPC: isize = 28;
+    pub const ENAMETOOLONG: isize = 36;
+}
+
+bitflags! {
+    /// open的flags，取值与Linux一致
+    pub struct OpenFlags: u32 {
+        const RDONLY = 0;
+        const WRONLY = 1 << 0;
+        const RDWR = 1 << 1;
+        const CREATE = 1 << 6;
+        const TRUNC = 1 << 9;
+        const APPEND = 1 << 10;
+        const DIRECTORY = 1 << 16;
+    }
+}
+
+bitflags! {
+    /// 文件类型
+    pub struct StatMode: u32 {
+        const NULL = 0;
+        const DIR = 0o040000;
+        const CHR = 0o020000;
+        const FILE = 0o100000;
+    }
+}
+
+/// fstat得到的文件信息，与内核中的定义一致
+#[repr(C)]
+#[derive(Debug)]
+pub struct Stat {
+    /// 文件所在的设备
+    pub dev: u64,
+    /// inode编号
+    pub ino: u64,
+    /// 文件类型
+    pub mode: StatMode,
+    /// 硬链接数
+    pub nlink: u32,
+    /// 文件大小
+    pub size: u64,
+    pad: [u64; 6],
+}
+
+impl Stat {
+    pub fn new() -> Self {
+        Self {
+            dev: 0,
+            ino: 0,
+            mode: StatMode::NULL,
+            nlink: 0,
+            size: 0,
+            pad: [0; 6],
+        }
+    }
+}
+
+impl Default for Stat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 打开文件，返回文件描述符，失败时返回负的errno；path必须以'\0'结尾
+pub fn open(path: &str, flags: OpenFlags) -> isize {
+    sys_openat(AT_FDCWD, path, flags.bits())
+}
+/// 为old_path创建硬链接new_path，两个路径都必须以'\0'结尾
+pub fn link(old_path: &str, new_path: &str) -> isize {
+    sys_linkat(AT_FDCWD, old_path, AT_FDCWD, new_path, 0)
+}
+/// 删除链接path，path必须以'\0'结尾
+pub fn unlink(path: &str) -> isize {
+    sys_unlinkat(AT_FDCWD, path, 0)
+}
+/// 创建目录，path必须以'\0'结尾
+pub fn mkdir(path: &str) -> isize {
+    sys_mkdirat(AT_FDCWD, path, 0o755)
+}
+/// 把fstype类型的文件系统挂载到target，data是以逗号分隔的选项(如tmpfs的"size=64k")，
+/// 所有字符串都必须以'\0'结尾
+pub fn mount(source: &str, target: &str, fstype: &str, data: Option<&str>) -> isize {
+    sys_mount(source, target, fstype, 0, data)
+}
+/// 卸载target上的文件系统，target必须以'\0'结尾
+pub fn umount(target: &str) -> isize {
+    sys_umount2(target, 0)
+}
+/// 改变当前目录，path必须以'\0'结尾
+pub fn chdir(path: &str) -> isize {
+    sys_chdir(path)
+}
+/// 把当前目录的绝对路径读到buf中，返回路径，buf放不下时返回None
+pub fn getcwd(buf: &mut [u8]) -> Option<&str> {
+    let len = sys_getcwd(buf);
+    if len <= 0 {
+        return None;
+    }
+    core::str::from_utf8(&buf[..len as usize - 1]).ok()
+}
+/// 读取目录fd中的目录项，返回写入buf的字节数，用parse_dirents解析
+pub fn getdents(fd: usize, buf: &mut [u8]) -> isize {
+    sys_getdents64(fd, buf)
+}
+
+/// getdents读到的一个目录项
+pub struct Dirent<'a> {
+    pub ino: u64,
+    pub is_dir: bool,
+    pub name: &'a str,
+}
+
+/// 遍历getdents写入buf的linux_dirent64
+pub struct DirentIter<'a> {
+    buf: &'a [u8],
+}
+
+/// 解析getdents写入的len字节
+pub fn parse_dirents(buf: &[u8], len: usize) -> DirentIter<'_> {
+    DirentIter { buf: &buf[..len] }
+}
+
+impl<'a> Iterator for DirentIter<'a> {
+    type Item = Dirent<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        // d_ino: u64, d_off: i64, d_reclen: u16, d_type: u8, d_name
+        const DT_DIR: u8 = 4;
+        if self.buf.len() < 19 {
+            return None;
+        }
+        let ino = u64::from_ne_bytes(self.buf[0..8].try_into().unwrap());
+        let reclen = u16::from_ne_bytes(self.buf[16..18].try_into().unwrap()) as usize;
+        let d_type = self.buf[18];
+        let name = &self.buf[19..reclen];
+        let name_len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        let dirent = Dirent {
+            ino,
+            is_dir: d_type == DT_DIR,
+            name: core::str::from_utf8(&name[..name_len]).unwrap_or(""),
+        };
+        self.buf = &self.buf[reclen..];
+        Some(dirent)
+    }
+}
+pub fn close(fd: usize) -> isize {
+    sys_close(fd)
+}
+/// 让newfd指向oldfd的文件，newfd原来打开的文件被关闭；返回newfd
+pub fn dup2(oldfd: usize, newfd: usize) -> isize {
+    if oldfd == newfd {
+        return if fstat(oldfd, &mut Stat::new()) == 0 { newfd as isize } else { -errno::EBADF };
+    }
+    sys_dup3(oldfd, newfd, 0)
+}
+/// 获取fd的文件信息
+pub fn fstat(fd: usize, st: &mut Stat) -> isize {
+    sys_fstat(fd, st)
+}
+/// 读取fd，stdin没有输入时阻塞，返回读到的字节数
+pub fn read(fd: usize, buf: &mut [u8]) -> isize {
+    sys_read(fd, buf)
+}
 pub fn write(fd: usize, buf: &[u8]) -> isize {
     sys_write(fd, buf)
 }
+/// 把内核块缓存中修改过的块写回磁盘
+pub fn sync() -> isize {
+    sys_sync()
+}
 pub fn exit(exit_code: i32) -> isize {
     sys_exit(exit_code)
-}
\ No newline at end of file
+}
+/// 关机，测试程序可以用failure把测试结果作为QEMU的退出码带给宿主机
+pub fn shutdown(failure: bool) -> ! {
+    sys_shutdown(failure);
+    panic!("unreachable after sys_shutdown!");
+}
+/// 重启，warm为false时冷重启
+pub fn reboot(warm: bool, failure: bool) -> ! {
+    sys_reboot(warm, failure);
+    panic!("unreachable after sys_reboot!");
+}
+
+/// 把内核日志缓冲区中最新的内容读到buf中，clear为true时读完后清空，返回读到的字节数
+pub fn dmesg(buf: &mut [u8], clear: bool) -> isize {
+    let action = if clear {
+        SYSLOG_ACTION_READ_CLEAR
+    } else {
+        SYSLOG_ACTION_READ_ALL
+    };
+    sys_syslog(action, buf)
+}
+/// 内核日志缓冲区的大小
+pub fn dmesg_buffer_size() -> isize {
+    sys_syslog(SYSLOG_ACTION_SIZE_BUFFER, &mut [])
+}
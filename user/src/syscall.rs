@@ -1,16 +1,38 @@
 use core::arch::asm;
 
+const SYSCALL_GETCWD: usize = 17;
+const SYSCALL_DUP3: usize = 24;
+const SYSCALL_MKDIRAT: usize = 34;
+const SYSCALL_UNLINKAT: usize = 35;
+const SYSCALL_LINKAT: usize = 37;
+const SYSCALL_UMOUNT2: usize = 39;
+const SYSCALL_MOUNT: usize = 40;
+const SYSCALL_CHDIR: usize = 49;
+const SYSCALL_OPENAT: usize = 56;
+const SYSCALL_CLOSE: usize = 57;
+const SYSCALL_GETDENTS64: usize = 61;
+const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
+const SYSCALL_FSTAT: usize = 80;
+const SYSCALL_SYNC: usize = 81;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_SYSLOG: usize = 116;
 const SYSCALL_YIELD: usize = 124;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_MUNMAP: usize = 215;
 const SYSCALL_MMAP: usize = 222;
 const SYSCALL_SET_PRIORITY: usize = 140;
 const SYSCALL_TASK_INFO: usize = 410;
+const SYSCALL_SHUTDOWN: usize = 2000;
+const SYSCALL_REBOOT: usize = 2001;
 
 
 fn sys_call(syscall_number:usize, args:[usize;3]) -> isize {
+    sys_call6(syscall_number, [args[0], args[1], args[2], 0, 0, 0])
+}
+
+/// 参数多于3个的系统调用，最多使用a0-a5
+fn sys_call6(syscall_number:usize, args:[usize;6]) -> isize {
     let mut ret;
     unsafe {
         asm!(
@@ -19,6 +41,9 @@ fn sys_call(syscall_number:usize, args:[usize;3]) -> isize {
         inlateout("a0") args[0] => ret,
         in("a1") args[1],
         in("a2") args[2],
+        in("a3") args[3],
+        in("a4") args[4],
+        in("a5") args[5],
         );
     }
     return ret;
@@ -28,7 +53,98 @@ pub fn sys_exit(xstate:i32) -> isize {
     sys_call(SYSCALL_EXIT, [xstate as usize,0,0])
 }
 
+/// 打开文件的dirfd，相对于当前目录
+pub const AT_FDCWD: isize = -100;
+
+pub fn sys_openat(dirfd: isize, path: &str, flags: u32) -> isize {
+    sys_call(SYSCALL_OPENAT, [dirfd as usize, path.as_ptr() as usize, flags as usize])
+}
+
+pub fn sys_linkat(olddirfd: isize, oldpath: &str, newdirfd: isize, newpath: &str, flags: u32) -> isize {
+    sys_call6(
+        SYSCALL_LINKAT,
+        [
+            olddirfd as usize,
+            oldpath.as_ptr() as usize,
+            newdirfd as usize,
+            newpath.as_ptr() as usize,
+            flags as usize,
+            0,
+        ],
+    )
+}
+
+pub fn sys_unlinkat(dirfd: isize, path: &str, flags: u32) -> isize {
+    sys_call(SYSCALL_UNLINKAT, [dirfd as usize, path.as_ptr() as usize, flags as usize])
+}
+
+pub fn sys_mkdirat(dirfd: isize, path: &str, mode: u32) -> isize {
+    sys_call(SYSCALL_MKDIRAT, [dirfd as usize, path.as_ptr() as usize, mode as usize])
+}
+
+pub fn sys_mount(source: &str, target: &str, fstype: &str, flags: usize, data: Option<&str>) -> isize {
+    sys_call6(
+        SYSCALL_MOUNT,
+        [
+            source.as_ptr() as usize,
+            target.as_ptr() as usize,
+            fstype.as_ptr() as usize,
+            flags,
+            data.map_or(0, |data| data.as_ptr() as usize),
+            0,
+        ],
+    )
+}
+
+pub fn sys_umount2(target: &str, flags: usize) -> isize {
+    sys_call(SYSCALL_UMOUNT2, [target.as_ptr() as usize, flags, 0])
+}
+
+pub fn sys_chdir(path: &str) -> isize {
+    sys_call(SYSCALL_CHDIR, [path.as_ptr() as usize, 0, 0])
+}
+
+pub fn sys_getcwd(buffer: &mut [u8]) -> isize {
+    sys_call(SYSCALL_GETCWD, [buffer.as_mut_ptr() as usize, buffer.len(), 0])
+}
+
+pub fn sys_getdents64(fd: usize, buffer: &mut [u8]) -> isize {
+    sys_call(SYSCALL_GETDENTS64, [fd, buffer.as_mut_ptr() as usize, buffer.len()])
+}
+
+pub fn sys_close(fd: usize) -> isize {
+    sys_call(SYSCALL_CLOSE, [fd, 0, 0])
+}
+
+pub fn sys_dup3(oldfd: usize, newfd: usize, flags: u32) -> isize {
+    sys_call(SYSCALL_DUP3, [oldfd, newfd, flags as usize])
+}
+
+pub fn sys_fstat(fd: usize, st: &mut crate::Stat) -> isize {
+    sys_call(SYSCALL_FSTAT, [fd, st as *mut _ as usize, 0])
+}
+
+pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
+    sys_call(SYSCALL_READ, [fd, buffer.as_mut_ptr() as usize, buffer.len()])
+}
+
 pub fn sys_write(fd: usize, buffer: &[u8]) -> isize {
     //使用as_ptr获得原始指针
     sys_call(SYSCALL_WRITE,[fd,buffer.as_ptr() as usize,buffer.len()])
-}
\ No newline at end of file
+}
+
+pub fn sys_sync() -> isize {
+    sys_call(SYSCALL_SYNC, [0, 0, 0])
+}
+
+pub fn sys_shutdown(failure: bool) -> isize {
+    sys_call(SYSCALL_SHUTDOWN, [failure as usize, 0, 0])
+}
+
+pub fn sys_reboot(warm: bool, failure: bool) -> isize {
+    sys_call(SYSCALL_REBOOT, [warm as usize, failure as usize, 0])
+}
+
+pub fn sys_syslog(action: usize, buffer: &mut [u8]) -> isize {
+    sys_call(SYSCALL_SYSLOG, [action, buffer.as_mut_ptr() as usize, buffer.len()])
+}